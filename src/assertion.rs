@@ -0,0 +1,417 @@
+use std::fmt;
+use std::str::FromStr;
+
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderName};
+use serde_json::Value;
+
+use crate::model::{CheckFailure, MonitoredServiceError};
+use crate::settings::AssertionConfig;
+
+/// Compiled assertion evaluated against an HTTP response
+#[derive(Debug, Clone)]
+pub enum Assertion {
+    BodyContains(String),
+    BodyNotContains(String),
+    BodyMatches(Regex),
+    Header {
+        name: HeaderName,
+        value: Option<String>,
+    },
+}
+
+impl Assertion {
+    /// Whether the assertion needs the response body to be downloaded
+    pub fn needs_body(&self) -> bool {
+        !matches!(self, Assertion::Header { .. })
+    }
+
+    pub fn evaluate(&self, headers: &HeaderMap, body: &str) -> Result<(), CheckFailure> {
+        let passed = match self {
+            Assertion::BodyContains(value) => body.contains(value.as_str()),
+            Assertion::BodyNotContains(value) => !body.contains(value.as_str()),
+            Assertion::BodyMatches(pattern) => pattern.is_match(body),
+            Assertion::Header { name, value } => match (headers.get(name), value) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some(actual), Some(expected)) => actual
+                    .to_str()
+                    .map(|actual| actual == expected)
+                    .unwrap_or(false),
+            },
+        };
+
+        if passed {
+            Ok(())
+        } else {
+            Err(CheckFailure::AssertionFailed(self.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Assertion::BodyContains(value) => write!(f, "body contains {:?}", value),
+            Assertion::BodyNotContains(value) => write!(f, "body does not contain {:?}", value),
+            Assertion::BodyMatches(pattern) => write!(f, "body matches /{}/", pattern),
+            Assertion::Header { name, value: None } => write!(f, "header {} is present", name),
+            Assertion::Header {
+                name,
+                value: Some(value),
+            } => write!(f, "header {} equals {:?}", name, value),
+        }
+    }
+}
+
+impl TryFrom<&AssertionConfig> for Assertion {
+    type Error = MonitoredServiceError;
+
+    fn try_from(config: &AssertionConfig) -> Result<Self, Self::Error> {
+        match config {
+            AssertionConfig::BodyContains { value } => Ok(Assertion::BodyContains(value.clone())),
+            AssertionConfig::BodyNotContains { value } => {
+                Ok(Assertion::BodyNotContains(value.clone()))
+            }
+            AssertionConfig::BodyMatches { pattern } => Regex::new(pattern)
+                .map(Assertion::BodyMatches)
+                .map_err(|e| MonitoredServiceError::InvalidAssertion(e.to_string())),
+            AssertionConfig::Header { name, value } => HeaderName::from_bytes(name.as_bytes())
+                .map(|name| Assertion::Header {
+                    name,
+                    value: value.clone(),
+                })
+                .map_err(|e| MonitoredServiceError::InvalidAssertion(e.to_string())),
+        }
+    }
+}
+
+/// Comparison operator of a JSON assertion
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Comparison {
+    // Two character operators go first so `>=` is not taken for `>`
+    const OPERATORS: [(&'static str, Comparison); 6] = [
+        ("==", Comparison::Eq),
+        ("!=", Comparison::Ne),
+        (">=", Comparison::Ge),
+        ("<=", Comparison::Le),
+        (">", Comparison::Gt),
+        ("<", Comparison::Lt),
+    ];
+
+    fn compare(&self, actual: &Value, expected: &Value) -> bool {
+        let numbers = actual.as_f64().zip(expected.as_f64());
+        match (self, numbers) {
+            (Comparison::Eq, Some((a, b))) => a == b,
+            (Comparison::Ne, Some((a, b))) => a != b,
+            (Comparison::Eq, None) => actual == expected,
+            (Comparison::Ne, None) => actual != expected,
+            (Comparison::Gt, Some((a, b))) => a > b,
+            (Comparison::Ge, Some((a, b))) => a >= b,
+            (Comparison::Lt, Some((a, b))) => a < b,
+            (Comparison::Le, Some((a, b))) => a <= b,
+            // Ordering is defined for numbers only
+            (_, None) => false,
+        }
+    }
+}
+
+/// Single step of a JSON path
+#[derive(Debug, Clone, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+/// Subset of JSONPath selecting values by keys, array indexes and wildcards,
+/// e.g. `$.status`, `$.checks[*].healthy` or `$['db'].latency`
+#[derive(Debug, Clone, PartialEq)]
+struct JsonPath {
+    segments: Vec<PathSegment>,
+}
+
+impl JsonPath {
+    fn query<'a>(&self, document: &'a Value) -> Vec<&'a Value> {
+        let mut nodes = vec![document];
+        for segment in &self.segments {
+            nodes = nodes
+                .into_iter()
+                .flat_map(|node| match (segment, node) {
+                    (PathSegment::Key(key), Value::Object(map)) => {
+                        map.get(key).into_iter().collect()
+                    }
+                    (PathSegment::Index(index), Value::Array(items)) => {
+                        items.get(*index).into_iter().collect()
+                    }
+                    (PathSegment::Wildcard, Value::Array(items)) => items.iter().collect(),
+                    (PathSegment::Wildcard, Value::Object(map)) => map.values().collect(),
+                    _ => Vec::new(),
+                })
+                .collect();
+        }
+        nodes
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid JSON path {:?}", s);
+        let mut rest = s.strip_prefix('$').ok_or_else(invalid)?;
+        let mut segments = Vec::new();
+
+        while !rest.is_empty() {
+            if let Some(after_dot) = rest.strip_prefix('.') {
+                let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+                let key = &after_dot[..end];
+                segments.push(match key {
+                    "*" => PathSegment::Wildcard,
+                    key if is_plain_key(key) => PathSegment::Key(key.to_string()),
+                    _ => return Err(invalid()),
+                });
+                rest = &after_dot[end..];
+            } else if let Some(after_bracket) = rest.strip_prefix('[') {
+                let end = after_bracket.find(']').ok_or_else(invalid)?;
+                let selector = after_bracket[..end].trim();
+                segments.push(if selector == "*" {
+                    PathSegment::Wildcard
+                } else if let Ok(index) = selector.parse::<usize>() {
+                    PathSegment::Index(index)
+                } else if selector.len() >= 2
+                    && (selector.starts_with('\'') && selector.ends_with('\'')
+                        || selector.starts_with('"') && selector.ends_with('"'))
+                {
+                    PathSegment::Key(selector[1..selector.len() - 1].to_string())
+                } else {
+                    return Err(invalid());
+                });
+                rest = &after_bracket[end + 1..];
+            } else {
+                return Err(invalid());
+            }
+        }
+
+        Ok(Self { segments })
+    }
+}
+
+/// Keys in dot notation are limited to identifier-like names, anything else
+/// has to use the bracket notation
+fn is_plain_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Assertion evaluated against a JSON response body
+///
+/// Written as `<path> <operator> <JSON value>`, e.g. `$.status == "ok"` or
+/// `$.queue.size < 100`. Every value selected by the path has to satisfy the
+/// comparison. A bare path only asserts that it selects at least one value.
+#[derive(Debug, Clone)]
+pub struct JsonAssertion {
+    expression: String,
+    path: JsonPath,
+    comparison: Option<(Comparison, Value)>,
+}
+
+impl JsonAssertion {
+    pub fn evaluate(&self, document: &Value) -> Result<(), CheckFailure> {
+        let nodes = self.path.query(document);
+        if nodes.is_empty() {
+            return Err(CheckFailure::AssertionFailed(format!(
+                "{} (no value found)",
+                self.expression
+            )));
+        }
+
+        if let Some((comparison, expected)) = &self.comparison {
+            if let Some(actual) = nodes
+                .into_iter()
+                .find(|actual| !comparison.compare(actual, expected))
+            {
+                return Err(CheckFailure::AssertionFailed(format!(
+                    "{} (got {})",
+                    self.expression, actual
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for JsonAssertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+impl FromStr for JsonAssertion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expression = s.trim();
+        let (path, comparison) = match find_operator(expression) {
+            Some((index, operator, comparison)) => {
+                let value = expression[index + operator.len()..].trim();
+                let expected = serde_json::from_str::<Value>(value)
+                    .map_err(|_| format!("{:?} is not a valid JSON value", value))?;
+                (&expression[..index], Some((comparison, expected)))
+            }
+            None => (expression, None),
+        };
+
+        let path = JsonPath::from_str(path.trim())?;
+
+        Ok(Self {
+            expression: expression.to_string(),
+            path,
+            comparison,
+        })
+    }
+}
+
+/// Find the first comparison operator outside of brackets and quoted strings
+fn find_operator(expression: &str) -> Option<(usize, &'static str, Comparison)> {
+    let mut depth = 0usize;
+    let mut quote = None;
+
+    for (index, c) in expression.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '[' | '(') => depth += 1,
+            (None, ']' | ')') => depth = depth.saturating_sub(1),
+            (None, _) if depth == 0 => {
+                let rest = &expression[index..];
+                if let Some((operator, comparison)) = Comparison::OPERATORS
+                    .iter()
+                    .find(|(operator, _)| rest.starts_with(operator))
+                {
+                    return Some((index, operator, *comparison));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn assertion(config: AssertionConfig) -> Assertion {
+        Assertion::try_from(&config).unwrap()
+    }
+
+    #[test]
+    fn test_body_assertions() {
+        let headers = HeaderMap::new();
+        let body = "<h1>Down for maintenance</h1>";
+
+        let contains = assertion(AssertionConfig::BodyContains {
+            value: "maintenance".to_string(),
+        });
+        let not_contains = assertion(AssertionConfig::BodyNotContains {
+            value: "maintenance".to_string(),
+        });
+        let matches = assertion(AssertionConfig::BodyMatches {
+            pattern: r"<h1>\w+".to_string(),
+        });
+
+        assert!(contains.evaluate(&headers, body).is_ok());
+        assert_eq!(
+            not_contains.evaluate(&headers, body),
+            Err(CheckFailure::AssertionFailed(
+                "body does not contain \"maintenance\"".to_string()
+            ))
+        );
+        assert!(matches.evaluate(&headers, body).is_ok());
+    }
+
+    #[test]
+    fn test_header_assertions() {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", HeaderValue::from_static("text/html"));
+
+        let present = assertion(AssertionConfig::Header {
+            name: "Content-Type".to_string(),
+            value: None,
+        });
+        let equals = assertion(AssertionConfig::Header {
+            name: "content-type".to_string(),
+            value: Some("application/json".to_string()),
+        });
+        let missing = assertion(AssertionConfig::Header {
+            name: "x-served-by".to_string(),
+            value: None,
+        });
+
+        assert!(present.evaluate(&headers, "").is_ok());
+        assert_eq!(
+            equals.evaluate(&headers, "").unwrap_err().to_string(),
+            "assertion failed: header content-type equals \"application/json\""
+        );
+        assert!(missing.evaluate(&headers, "").is_err());
+    }
+
+    #[test]
+    fn test_json_assertions() {
+        let document = serde_json::json!({
+            "status": "ok",
+            "queue": { "size": 42 },
+            "checks": [
+                { "name": "db", "healthy": true },
+                { "name": "cache", "healthy": false }
+            ]
+        });
+
+        let passing = [
+            r#"$.status == "ok""#,
+            "$.queue.size < 100",
+            "$.queue.size >= 42.0",
+            "$.checks[0].healthy == true",
+            "$['queue'].size == 42",
+            r#"$.checks[*].name != "api""#,
+            "$.queue",
+        ];
+        for expression in passing {
+            let assertion = JsonAssertion::from_str(expression).unwrap();
+            assert!(assertion.evaluate(&document).is_ok(), "{}", expression);
+        }
+
+        let failing = JsonAssertion::from_str("$.checks[*].healthy == true").unwrap();
+        assert_eq!(
+            failing.evaluate(&document).unwrap_err().to_string(),
+            "assertion failed: $.checks[*].healthy == true (got false)"
+        );
+
+        let missing = JsonAssertion::from_str(r#"$.db != "down""#).unwrap();
+        assert!(missing.evaluate(&document).is_err());
+    }
+
+    #[test]
+    fn test_invalid_json_assertions() {
+        assert!(JsonAssertion::from_str("status == \"ok\"").is_err());
+        assert!(JsonAssertion::from_str("$.status == ok").is_err());
+        assert!(JsonAssertion::from_str("$.checks[first] == 1").is_err());
+        assert!(JsonAssertion::from_str("$..status == 1").is_err());
+    }
+}
@@ -0,0 +1,465 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UdpSocket;
+use tokio::time;
+
+use crate::check::tcp;
+use crate::model::{CheckFailure, MonitoredServiceError};
+use crate::settings::{DnsProtocol, DnsRecordType, ServiceConfig};
+
+/// Maximum number of compression pointers followed while decoding a name
+const MAX_POINTERS: usize = 16;
+
+/// Check of a DNS record served by a nameserver
+#[derive(Debug, PartialEq, Clone)]
+pub struct DnsCheck {
+    pub nameserver: SocketAddr,
+    pub domain: String,
+    pub record_type: DnsRecordType,
+    pub protocol: DnsProtocol,
+    // Values which have to be present among the answers (optional)
+    pub expected_records: Vec<String>,
+}
+
+impl DnsCheck {
+    /// Perform a single check attempt within the total timeout
+    pub async fn run(
+        &self,
+        timeout: Duration,
+        connect_timeout: Duration,
+    ) -> Result<(), CheckFailure> {
+        time::timeout(timeout, self.probe(connect_timeout))
+            .await
+            .map_err(|_| CheckFailure::Timeout(timeout))?
+    }
+
+    async fn probe(&self, connect_timeout: Duration) -> Result<(), CheckFailure> {
+        let id = fastrand::u16(..);
+        let query = encode_query(id, &self.domain, self.record_type)?;
+
+        let mut response = match self.protocol {
+            DnsProtocol::Udp => query_udp(self.nameserver, &query).await?,
+            DnsProtocol::Tcp => query_tcp(self.nameserver, &query, connect_timeout).await?,
+        };
+        if self.protocol == DnsProtocol::Udp && is_truncated(&response) {
+            // Answer doesn't fit into a datagram, ask again over TCP
+            response = query_tcp(self.nameserver, &query, connect_timeout).await?;
+        }
+
+        let records = decode_response(id, &response, self.record_type)?;
+        if records.is_empty() {
+            return Err(CheckFailure::Dns(format!(
+                "no {} records for {}",
+                self.record_type, self.domain
+            )));
+        }
+
+        let missing: Vec<&String> = self
+            .expected_records
+            .iter()
+            .filter(|expected| {
+                let expected = normalize(self.record_type, expected);
+                !records.contains(&expected)
+            })
+            .collect();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(CheckFailure::AssertionFailed(format!(
+                "{} records {:?} missing (got {:?})",
+                self.record_type, missing, records
+            )))
+        }
+    }
+}
+
+async fn query_udp(nameserver: SocketAddr, query: &[u8]) -> Result<Vec<u8>, CheckFailure> {
+    let dns_error = |e: std::io::Error| CheckFailure::Connection(e.to_string());
+    let local_address: SocketAddr = match nameserver {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+
+    let socket = UdpSocket::bind(local_address).await.map_err(dns_error)?;
+    socket.connect(nameserver).await.map_err(dns_error)?;
+    socket.send(query).await.map_err(dns_error)?;
+
+    let mut buffer = vec![0u8; 4096];
+    let read = socket.recv(&mut buffer).await.map_err(dns_error)?;
+    buffer.truncate(read);
+
+    Ok(buffer)
+}
+
+async fn query_tcp(
+    nameserver: SocketAddr,
+    query: &[u8],
+    connect_timeout: Duration,
+) -> Result<Vec<u8>, CheckFailure> {
+    let dns_error = |e: std::io::Error| CheckFailure::Connection(e.to_string());
+    let mut stream = tcp::connect(
+        &nameserver.ip().to_string(),
+        nameserver.port(),
+        connect_timeout,
+    )
+    .await?;
+
+    // Messages over TCP are prefixed by their length
+    let mut message = (query.len() as u16).to_be_bytes().to_vec();
+    message.extend_from_slice(query);
+    stream.write_all(&message).await.map_err(dns_error)?;
+
+    let length = stream.read_u16().await.map_err(dns_error)?;
+    let mut buffer = vec![0u8; usize::from(length)];
+    stream.read_exact(&mut buffer).await.map_err(dns_error)?;
+
+    Ok(buffer)
+}
+
+/// Build a recursive query for a single question
+fn encode_query(
+    id: u16,
+    domain: &str,
+    record_type: DnsRecordType,
+) -> Result<Vec<u8>, CheckFailure> {
+    let mut query = Vec::with_capacity(512);
+    query.extend_from_slice(&id.to_be_bytes());
+    // Standard query with recursion desired
+    query.extend_from_slice(&0x0100u16.to_be_bytes());
+    // One question, no answer, authority or additional records
+    query.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+
+    for label in domain.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(CheckFailure::Dns(format!("invalid domain {}", domain)));
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&record_type.code().to_be_bytes());
+    // Class IN
+    query.extend_from_slice(&1u16.to_be_bytes());
+
+    Ok(query)
+}
+
+fn is_truncated(response: &[u8]) -> bool {
+    response.len() > 2 && response[2] & 0x02 != 0
+}
+
+/// Decode answers of the requested type from a response
+fn decode_response(
+    id: u16,
+    response: &[u8],
+    record_type: DnsRecordType,
+) -> Result<Vec<String>, CheckFailure> {
+    let malformed = || CheckFailure::Dns("malformed response".to_string());
+    let header = response.get(..12).ok_or_else(malformed)?;
+
+    if u16::from_be_bytes([header[0], header[1]]) != id || header[2] & 0x80 == 0 {
+        return Err(malformed());
+    }
+    match header[3] & 0x0f {
+        0 => {}
+        2 => return Err(CheckFailure::Dns("SERVFAIL".to_string())),
+        3 => return Err(CheckFailure::Dns("NXDOMAIN".to_string())),
+        5 => return Err(CheckFailure::Dns("REFUSED".to_string())),
+        code => return Err(CheckFailure::Dns(format!("response code {}", code))),
+    }
+
+    let questions = u16::from_be_bytes([header[4], header[5]]);
+    let answers = u16::from_be_bytes([header[6], header[7]]);
+
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = skip_name(response, offset).ok_or_else(malformed)? + 4;
+    }
+
+    let mut records = Vec::new();
+    for _ in 0..answers {
+        offset = skip_name(response, offset).ok_or_else(malformed)?;
+        let fixed = response.get(offset..offset + 10).ok_or_else(malformed)?;
+        let answer_type = u16::from_be_bytes([fixed[0], fixed[1]]);
+        let length = usize::from(u16::from_be_bytes([fixed[8], fixed[9]]));
+        let data_offset = offset + 10;
+        let data = response
+            .get(data_offset..data_offset + length)
+            .ok_or_else(malformed)?;
+
+        if answer_type == record_type.code() {
+            let record = match record_type {
+                DnsRecordType::A => <[u8; 4]>::try_from(data)
+                    .map(|octets| Ipv4Addr::from(octets).to_string())
+                    .ok(),
+                DnsRecordType::Aaaa => <[u8; 16]>::try_from(data)
+                    .map(|octets| Ipv6Addr::from(octets).to_string())
+                    .ok(),
+                DnsRecordType::Cname => read_name(response, data_offset),
+                DnsRecordType::Mx => data.get(..2).and_then(|preference| {
+                    read_name(response, data_offset + 2).map(|exchange| {
+                        format!(
+                            "{} {}",
+                            u16::from_be_bytes([preference[0], preference[1]]),
+                            exchange
+                        )
+                    })
+                }),
+                DnsRecordType::Txt => read_character_strings(data),
+            };
+            records.push(record.ok_or_else(malformed)?);
+        }
+
+        offset = data_offset + length;
+    }
+
+    Ok(records)
+}
+
+/// Return the offset right after the (possibly compressed) name
+fn skip_name(message: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let length = *message.get(offset)?;
+        match length {
+            0 => return Some(offset + 1),
+            length if length & 0xc0 == 0xc0 => return Some(offset + 2),
+            length => offset += usize::from(length) + 1,
+        }
+    }
+}
+
+/// Decode a (possibly compressed) name into its dotted lowercase form
+fn read_name(message: &[u8], mut offset: usize) -> Option<String> {
+    let mut labels = Vec::new();
+    let mut pointers = 0;
+
+    loop {
+        let length = *message.get(offset)?;
+        if length == 0 {
+            break;
+        } else if length & 0xc0 == 0xc0 {
+            pointers += 1;
+            if pointers > MAX_POINTERS {
+                return None;
+            }
+            let low = *message.get(offset + 1)?;
+            offset = usize::from(u16::from_be_bytes([length & 0x3f, low]));
+        } else {
+            let label = message.get(offset + 1..offset + 1 + usize::from(length))?;
+            labels.push(String::from_utf8_lossy(label).to_lowercase());
+            offset += usize::from(length) + 1;
+        }
+    }
+
+    Some(labels.join("."))
+}
+
+/// Join the character strings of a TXT record
+fn read_character_strings(mut data: &[u8]) -> Option<String> {
+    let mut text = String::new();
+    while let Some((&length, rest)) = data.split_first() {
+        let chunk = rest.get(..usize::from(length))?;
+        text.push_str(&String::from_utf8_lossy(chunk));
+        data = &rest[usize::from(length)..];
+    }
+    Some(text)
+}
+
+/// Bring a configured value to the form answers are decoded to
+fn normalize(record_type: DnsRecordType, value: &str) -> String {
+    match record_type {
+        DnsRecordType::A | DnsRecordType::Aaaa => value
+            .parse::<IpAddr>()
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|_| value.to_string()),
+        DnsRecordType::Cname | DnsRecordType::Mx => {
+            value.trim().trim_end_matches('.').to_lowercase()
+        }
+        DnsRecordType::Txt => value.to_string(),
+    }
+}
+
+/// Parse a nameserver address, the port defaults to 53
+pub fn parse_nameserver(nameserver: &str) -> Option<SocketAddr> {
+    nameserver.parse::<SocketAddr>().ok().or_else(|| {
+        nameserver
+            .parse::<IpAddr>()
+            .ok()
+            .map(|ip| SocketAddr::new(ip, 53))
+    })
+}
+
+impl TryFrom<&ServiceConfig> for DnsCheck {
+    type Error = MonitoredServiceError;
+
+    fn try_from(service: &ServiceConfig) -> Result<Self, Self::Error> {
+        let nameserver = service
+            .nameserver
+            .as_deref()
+            .ok_or(MonitoredServiceError::MissingField("nameserver"))?;
+        let domain = service
+            .domain
+            .clone()
+            .ok_or(MonitoredServiceError::MissingField("domain"))?;
+
+        Ok(Self {
+            nameserver: parse_nameserver(nameserver)
+                .ok_or_else(|| MonitoredServiceError::InvalidAddress(nameserver.to_string()))?,
+            domain,
+            record_type: service.record_type,
+            protocol: service.dns_protocol,
+            expected_records: service.expected_records.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Build a response to the query with the given answers, the answers refer
+    /// to the queried name by a compression pointer
+    fn respond(query: &[u8], rcode: u8, answers: &[(DnsRecordType, Vec<u8>)]) -> Vec<u8> {
+        let mut response = query.to_vec();
+        response[2] = 0x81;
+        response[3] = 0x80 | rcode;
+        response[6..8].copy_from_slice(&(answers.len() as u16).to_be_bytes());
+        for (record_type, data) in answers {
+            response.extend_from_slice(&[0xc0, 0x0c]);
+            response.extend_from_slice(&record_type.code().to_be_bytes());
+            response.extend_from_slice(&[0, 1, 0, 0, 0x0e, 0x10]);
+            response.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            response.extend_from_slice(data);
+        }
+        response
+    }
+
+    fn answers_for(query: &[u8]) -> Vec<u8> {
+        let name_end = skip_name(query, 12).unwrap();
+        let domain = read_name(query, 12).unwrap();
+        let record_type = u16::from_be_bytes([query[name_end], query[name_end + 1]]);
+
+        match (domain.as_str(), record_type) {
+            ("example.com", 1) => respond(
+                query,
+                0,
+                &[
+                    (DnsRecordType::A, vec![192, 0, 2, 1]),
+                    (DnsRecordType::A, vec![192, 0, 2, 2]),
+                ],
+            ),
+            ("example.com", 15) => {
+                // Exchange `mail` followed by a pointer to the queried name
+                let data = vec![0, 10, 4, b'm', b'a', b'i', b'l', 0xc0, 0x0c];
+                respond(query, 0, &[(DnsRecordType::Mx, data)])
+            }
+            ("example.com", 16) => {
+                let data = b"\x0bv=spf1 -all".to_vec();
+                respond(query, 0, &[(DnsRecordType::Txt, data)])
+            }
+            ("example.com", _) => respond(query, 0, &[]),
+            _ => respond(query, 3, &[]),
+        }
+    }
+
+    /// Start a stand-in nameserver answering over UDP and TCP on the same port
+    async fn start_nameserver() -> SocketAddr {
+        let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = tcp.local_addr().unwrap();
+        let udp = UdpSocket::bind(address).await.unwrap();
+
+        tokio::spawn(async move {
+            let mut buffer = [0u8; 512];
+            while let Ok((read, peer)) = udp.recv_from(&mut buffer).await {
+                let _ = udp.send_to(&answers_for(&buffer[..read]), peer).await;
+            }
+        });
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = tcp.accept().await {
+                let length = stream.read_u16().await.unwrap();
+                let mut query = vec![0u8; usize::from(length)];
+                stream.read_exact(&mut query).await.unwrap();
+                let response = answers_for(&query);
+                stream
+                    .write_all(&(response.len() as u16).to_be_bytes())
+                    .await
+                    .unwrap();
+                stream.write_all(&response).await.unwrap();
+            }
+        });
+
+        address
+    }
+
+    fn dns_check(nameserver: SocketAddr, domain: &str, record_type: DnsRecordType) -> DnsCheck {
+        DnsCheck {
+            nameserver,
+            domain: domain.to_string(),
+            record_type,
+            protocol: DnsProtocol::Udp,
+            expected_records: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expected_records() {
+        let nameserver = start_nameserver().await;
+        let timeout = Duration::from_secs(1);
+
+        let mut check = dns_check(nameserver, "example.com", DnsRecordType::A);
+        check.expected_records = vec!["192.0.2.2".to_string()];
+        assert_eq!(check.run(timeout, timeout).await, Ok(()));
+
+        check.protocol = DnsProtocol::Tcp;
+        check.expected_records = vec!["192.0.2.3".to_string()];
+        assert_eq!(
+            check.run(timeout, timeout).await.unwrap_err().to_string(),
+            "assertion failed: A records [\"192.0.2.3\"] missing (got [\"192.0.2.1\", \"192.0.2.2\"])"
+        );
+
+        let mut check = dns_check(nameserver, "Example.com.", DnsRecordType::Mx);
+        check.expected_records = vec!["10 mail.example.com.".to_string()];
+        assert_eq!(check.run(timeout, timeout).await, Ok(()));
+
+        let mut check = dns_check(nameserver, "example.com", DnsRecordType::Txt);
+        check.expected_records = vec!["v=spf1 -all".to_string()];
+        assert_eq!(check.run(timeout, timeout).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_failed_resolution() {
+        let nameserver = start_nameserver().await;
+        let timeout = Duration::from_secs(1);
+
+        let check = dns_check(nameserver, "missing.example.com", DnsRecordType::A);
+        assert_eq!(
+            check.run(timeout, timeout).await,
+            Err(CheckFailure::Dns("NXDOMAIN".to_string()))
+        );
+
+        let check = dns_check(nameserver, "example.com", DnsRecordType::Aaaa);
+        assert_eq!(
+            check.run(timeout, timeout).await,
+            Err(CheckFailure::Dns(
+                "no AAAA records for example.com".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_nameserver() {
+        assert_eq!(
+            parse_nameserver("192.0.2.53"),
+            Some("192.0.2.53:53".parse().unwrap())
+        );
+        assert_eq!(
+            parse_nameserver("[2001:db8::53]:5353"),
+            Some("[2001:db8::53]:5353".parse().unwrap())
+        );
+        assert_eq!(parse_nameserver("ns.example.com"), None);
+    }
+}
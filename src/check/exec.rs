@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tokio::time;
+
+use crate::check::tcp::excerpt;
+use crate::model::{CheckFailure, MonitoredServiceError};
+use crate::settings::ServiceConfig;
+
+/// Maximum number of bytes kept from each output stream of the command
+const MAX_OUTPUT_SIZE: usize = 4096;
+
+/// Check running a local command, healthy when it exits with code 0
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExecCheck {
+    pub command: String,
+    pub args: Vec<String>,
+    // Variables added to the environment inherited from Fluxa
+    pub env: BTreeMap<String, String>,
+}
+
+impl ExecCheck {
+    /// Run the command once in its own process group, processes it leaves
+    /// behind are killed when it exits and the whole group when the timeout
+    /// elapses
+    pub async fn run(&self, timeout: Duration) -> Result<(), CheckFailure> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .envs(&self.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| CheckFailure::CommandNotStarted(e.to_string()))?;
+
+        let process_group = child.id();
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let finished = time::timeout(timeout, async {
+            // Background processes would keep the output pipes open
+            let exited = async {
+                let status = child.wait().await;
+                kill_process_group(process_group);
+                status
+            };
+            tokio::join!(exited, read_output(stdout), read_output(stderr))
+        })
+        .await;
+
+        let (status, stdout, stderr) = match finished {
+            Ok(finished) => finished,
+            Err(_) => {
+                kill_process_group(process_group);
+                // Waits for the killed process so it doesn't stay a zombie
+                let _ = child.kill().await;
+                return Err(CheckFailure::Timeout(timeout));
+            }
+        };
+        let status = status.map_err(|e| CheckFailure::CommandNotStarted(e.to_string()))?;
+        if status.success() {
+            return Ok(());
+        }
+
+        let status = match status.code() {
+            Some(code) => format!("exit code {}", code),
+            None => "termination by a signal".to_string(),
+        };
+        let output = [stdout, stderr]
+            .iter()
+            .map(|output| excerpt(output))
+            .filter(|output| !output.is_empty())
+            .collect::<Vec<_>>()
+            .join(" / ");
+
+        Err(CheckFailure::CommandFailed(if output.is_empty() {
+            status
+        } else {
+            format!("{} ({})", status, output)
+        }))
+    }
+}
+
+/// Kill every process of the group led by the command
+fn kill_process_group(process_group: Option<u32>) {
+    if let Some(process_group) = process_group.and_then(|id| libc::pid_t::try_from(id).ok()) {
+        // SAFETY: kill only sends a signal, a group without processes is an error
+        unsafe {
+            libc::kill(-process_group, libc::SIGKILL);
+        }
+    }
+}
+
+/// Read the beginning of an output stream and drain the rest, so the command
+/// never blocks on a full pipe
+async fn read_output(stream: Option<impl AsyncRead + Unpin>) -> Vec<u8> {
+    let mut output = Vec::new();
+    let Some(mut stream) = stream else {
+        return output;
+    };
+
+    let mut buffer = [0u8; 512];
+    while let Ok(read) = stream.read(&mut buffer).await {
+        if read == 0 {
+            break;
+        }
+        let kept = read.min(MAX_OUTPUT_SIZE.saturating_sub(output.len()));
+        output.extend_from_slice(&buffer[..kept]);
+    }
+
+    output
+}
+
+impl TryFrom<&ServiceConfig> for ExecCheck {
+    type Error = MonitoredServiceError;
+
+    fn try_from(service: &ServiceConfig) -> Result<Self, Self::Error> {
+        match &service.command {
+            Some(command) if !command.is_empty() => Ok(Self {
+                command: command.clone(),
+                args: service.args.clone(),
+                env: service.env.clone(),
+            }),
+            _ => Err(MonitoredServiceError::MissingField("command")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Instant;
+
+    fn shell(script: &str) -> ExecCheck {
+        ExecCheck {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: BTreeMap::from([("MOUNT".to_string(), "/data".to_string())]),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exit_code_and_output() {
+        let timeout = Duration::from_secs(5);
+
+        assert_eq!(shell(r#"test "$MOUNT" = /data"#).run(timeout).await, Ok(()));
+        assert_eq!(
+            shell("echo; echo '  /data is 97% full  ' >&2; exit 2")
+                .run(timeout)
+                .await,
+            Err(CheckFailure::CommandFailed(
+                "exit code 2 (/data is 97% full)".to_string()
+            ))
+        );
+        assert_eq!(
+            shell("exit 1").run(timeout).await,
+            Err(CheckFailure::CommandFailed("exit code 1".to_string()))
+        );
+        assert!(matches!(
+            ExecCheck {
+                command: "/nonexistent/check".to_string(),
+                args: Vec::new(),
+                env: BTreeMap::new(),
+            }
+            .run(timeout)
+            .await,
+            Err(CheckFailure::CommandNotStarted(_))
+        ));
+    }
+
+    /// Run the script, which writes the PID of a background process to the
+    /// file `$PID_FILE`, and return the result with the PID
+    async fn run_with_background(
+        name: &str,
+        script: &str,
+        timeout: Duration,
+    ) -> (Result<(), CheckFailure>, String) {
+        let pid_file = std::env::temp_dir().join(format!("fluxa-{}-{}", std::process::id(), name));
+        let mut check = shell(script);
+        check
+            .env
+            .insert("PID_FILE".to_string(), pid_file.display().to_string());
+
+        let result = check.run(timeout).await;
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        std::fs::remove_file(&pid_file).unwrap();
+        (result, pid.trim().to_string())
+    }
+
+    /// Whether the process exists and isn't a zombie
+    fn is_running(pid: &str) -> bool {
+        let output = std::process::Command::new("ps")
+            .args(["-o", "stat=", "-p", pid])
+            .output()
+            .unwrap();
+        let state = String::from_utf8_lossy(&output.stdout);
+        !state.trim().is_empty() && !state.trim().starts_with('Z')
+    }
+
+    #[tokio::test]
+    async fn test_command_is_killed_on_timeout() {
+        let timeout = Duration::from_millis(200);
+
+        let started = Instant::now();
+        let (result, pid) = run_with_background(
+            "timeout.pid",
+            r#"sleep 30 & echo $! > "$PID_FILE"; sleep 30"#,
+            timeout,
+        )
+        .await;
+
+        assert_eq!(result, Err(CheckFailure::Timeout(timeout)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        time::sleep(Duration::from_millis(100)).await;
+        assert!(!is_running(&pid), "process {} is still running", pid);
+    }
+
+    #[tokio::test]
+    async fn test_background_processes_are_killed_on_exit() {
+        let timeout = Duration::from_secs(5);
+
+        let started = Instant::now();
+        let (result, pid) = run_with_background(
+            "background.pid",
+            r#"sleep 30 & echo $! > "$PID_FILE""#,
+            timeout,
+        )
+        .await;
+
+        assert_eq!(result, Ok(()));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        time::sleep(Duration::from_millis(100)).await;
+        assert!(!is_running(&pid), "process {} is still running", pid);
+    }
+}
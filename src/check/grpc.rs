@@ -0,0 +1,293 @@
+use std::time::Duration;
+
+use http_body_util::BodyExt;
+use reqwest::header::{HeaderMap, CONTENT_TYPE, TE};
+use reqwest::{Client, Url};
+
+use crate::check::http::request_failure;
+use crate::model::{CheckFailure, MonitoredServiceError};
+use crate::settings::ServiceConfig;
+
+/// Path of the standard health checking method
+const HEALTH_CHECK_PATH: &str = "grpc.health.v1.Health/Check";
+
+/// Check of a gRPC server implementing the standard `grpc.health.v1.Health` service
+#[derive(Debug)]
+pub struct GrpcCheck {
+    pub url: String,
+    // Checked service, the overall server health when empty
+    pub service: String,
+}
+
+impl GrpcCheck {
+    /// Perform a single `Check` call, the server has to answer `SERVING`. The
+    /// client talks HTTP/2 with prior knowledge, also for `http://` urls
+    pub async fn run(
+        &self,
+        client: &Client,
+        timeout: Duration,
+        connect_timeout: Duration,
+    ) -> Result<(), CheckFailure> {
+        let request_failure = |error| request_failure(error, timeout, connect_timeout);
+
+        let response = client
+            .post(format!(
+                "{}/{}",
+                self.url.trim_end_matches('/'),
+                HEALTH_CHECK_PATH
+            ))
+            .header(CONTENT_TYPE, "application/grpc")
+            .header(TE, "trailers")
+            .body(frame(&encode_request(&self.service)))
+            .timeout(timeout)
+            .send()
+            .await
+            .map_err(request_failure)?;
+
+        let status = response.status().as_u16();
+        if status != 200 {
+            return Err(CheckFailure::UnexpectedStatus(status));
+        }
+
+        // Calls failing right away carry their status in the headers, others
+        // in the trailers after the response body
+        verify_grpc_status(response.headers())?;
+        let body = reqwest::Body::from(response)
+            .collect()
+            .await
+            .map_err(request_failure)?;
+        if let Some(trailers) = body.trailers() {
+            verify_grpc_status(trailers)?;
+        }
+
+        let body = body.to_bytes();
+        let message = unframe(&body)
+            .ok_or_else(|| CheckFailure::Grpc("empty or malformed response".to_string()))?;
+
+        match decode_serving_status(message) {
+            Some(1) => Ok(()),
+            Some(status) => Err(CheckFailure::Grpc(serving_status_name(status))),
+            None => Err(CheckFailure::Grpc("malformed response".to_string())),
+        }
+    }
+}
+
+fn verify_grpc_status(headers: &HeaderMap) -> Result<(), CheckFailure> {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    };
+
+    match header("grpc-status") {
+        "" | "0" => Ok(()),
+        code => Err(CheckFailure::Grpc(format!(
+            "status {} {}",
+            code,
+            header("grpc-message")
+        ))),
+    }
+}
+
+/// Encode `HealthCheckRequest { string service = 1; }`
+fn encode_request(service: &str) -> Vec<u8> {
+    let mut message = Vec::new();
+    if !service.is_empty() {
+        message.push(0x0a);
+        encode_varint(service.len() as u64, &mut message);
+        message.extend_from_slice(service.as_bytes());
+    }
+    message
+}
+
+/// Prefix a message with the uncompressed flag and its length
+fn frame(message: &[u8]) -> Vec<u8> {
+    let mut framed = vec![0];
+    framed.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    framed.extend_from_slice(message);
+    framed
+}
+
+/// The first message of a response body
+fn unframe(body: &[u8]) -> Option<&[u8]> {
+    let (&compressed, rest) = body.split_first()?;
+    if compressed != 0 || rest.len() < 4 {
+        return None;
+    }
+    let (length, rest) = rest.split_at(4);
+    let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+    rest.get(..length)
+}
+
+/// Read `status` of `HealthCheckResponse { ServingStatus status = 1; }`, a
+/// missing field means the default `UNKNOWN`
+fn decode_serving_status(mut message: &[u8]) -> Option<u64> {
+    let mut status = 0;
+    while !message.is_empty() {
+        let key = decode_varint(&mut message)?;
+        match (key >> 3, key & 0x07) {
+            (1, 0) => status = decode_varint(&mut message)?,
+            (_, 0) => {
+                decode_varint(&mut message)?;
+            }
+            (_, 1) => message = message.get(8..)?,
+            (_, 2) => {
+                let length = usize::try_from(decode_varint(&mut message)?).ok()?;
+                message = message.get(length..)?;
+            }
+            (_, 5) => message = message.get(4..)?,
+            _ => return None,
+        }
+    }
+    Some(status)
+}
+
+fn encode_varint(mut value: u64, output: &mut Vec<u8>) {
+    while value >= 0x80 {
+        output.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+fn decode_varint(input: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = input.split_first()?;
+        *input = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn serving_status_name(status: u64) -> String {
+    match status {
+        0 => "UNKNOWN".to_string(),
+        2 => "NOT_SERVING".to_string(),
+        3 => "SERVICE_UNKNOWN".to_string(),
+        other => format!("serving status {}", other),
+    }
+}
+
+impl TryFrom<&ServiceConfig> for GrpcCheck {
+    type Error = MonitoredServiceError;
+
+    fn try_from(service: &ServiceConfig) -> Result<Self, Self::Error> {
+        let url = service.url.clone().unwrap_or_default();
+        if !Url::parse(&url).is_ok_and(|parsed| matches!(parsed.scheme(), "http" | "https")) {
+            return Err(MonitoredServiceError::InvalidUrl(url));
+        }
+
+        Ok(Self {
+            url,
+            service: service.grpc_service.clone().unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::body::{Body, Bytes};
+    use axum::http::{HeaderValue, StatusCode};
+    use axum::{routing::post, Router};
+    use http_body_util::Empty;
+
+    /// Answer health checks like a gRPC server: the server itself is serving,
+    /// `billing` is not, `payments` fails after the response started and
+    /// other services are unknown
+    async fn health_check(body: Bytes) -> (HeaderMap, Body) {
+        let request = unframe(&body).unwrap();
+        let service = request.get(2..).unwrap_or_default();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+        let status = match service {
+            b"" => 1,
+            b"billing" => 2,
+            b"payments" => {
+                let mut trailers = HeaderMap::new();
+                trailers.insert("grpc-status", HeaderValue::from_static("14"));
+                trailers.insert("grpc-message", HeaderValue::from_static("database down"));
+                let body = Empty::<Bytes>::new().with_trailers(async { Some(Ok(trailers)) });
+                return (headers, Body::new(body));
+            }
+            _ => {
+                headers.insert("grpc-status", HeaderValue::from_static("5"));
+                headers.insert("grpc-message", HeaderValue::from_static("unknown service"));
+                return (headers, Body::empty());
+            }
+        };
+        (headers, Body::from(frame(&[0x08, status])))
+    }
+
+    /// Start a stand-in cleartext HTTP/2 gRPC server
+    async fn start_grpc_server() -> String {
+        let app = Router::new()
+            .route(&format!("/{}", HEALTH_CHECK_PATH), post(health_check))
+            .fallback(|| async { StatusCode::NOT_FOUND });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    fn grpc_check(url: &str, service: &str) -> GrpcCheck {
+        let config = ServiceConfig {
+            url: Some(url.to_string()),
+            grpc_service: Some(service.to_string()),
+            ..Default::default()
+        };
+        GrpcCheck::try_from(&config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_serving_status() {
+        let url = start_grpc_server().await;
+        let client = Client::builder().http2_prior_knowledge().build().unwrap();
+        let timeout = Duration::from_secs(1);
+
+        assert_eq!(
+            grpc_check(&url, "").run(&client, timeout, timeout).await,
+            Ok(())
+        );
+        assert_eq!(
+            grpc_check(&url, "billing")
+                .run(&client, timeout, timeout)
+                .await,
+            Err(CheckFailure::Grpc("NOT_SERVING".to_string()))
+        );
+        assert_eq!(
+            grpc_check(&url, "search")
+                .run(&client, timeout, timeout)
+                .await,
+            Err(CheckFailure::Grpc("status 5 unknown service".to_string()))
+        );
+        assert_eq!(
+            grpc_check(&url, "payments")
+                .run(&client, timeout, timeout)
+                .await,
+            Err(CheckFailure::Grpc("status 14 database down".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_message_encoding() {
+        assert_eq!(encode_request(""), Vec::<u8>::new());
+        assert_eq!(encode_request("db"), vec![0x0a, 2, b'd', b'b']);
+
+        let framed = frame(&[0x08, 0x01]);
+        assert_eq!(framed, vec![0, 0, 0, 0, 2, 0x08, 0x01]);
+        assert_eq!(unframe(&framed), Some(&[0x08, 0x01][..]));
+        assert_eq!(unframe(&framed[..6]), None);
+
+        // Unknown fields are skipped, a missing status means UNKNOWN
+        assert_eq!(decode_serving_status(&[0x12, 1, b'x', 0x08, 2]), Some(2));
+        assert_eq!(decode_serving_status(&[]), Some(0));
+        assert_eq!(decode_serving_status(&[0x08]), None);
+    }
+}
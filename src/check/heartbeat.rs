@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::model::{CheckFailure, MonitoredServiceError};
+use crate::settings::ServiceConfig;
+
+/// Last check-in received from a pushing job
+#[derive(Debug, PartialEq, Clone)]
+pub struct Ping {
+    pub received_at: Instant,
+    // Reason sent along with an explicit failure, `None` for a successful ping
+    pub failure: Option<String>,
+}
+
+/// Check-ins of a single heartbeat service
+#[derive(Debug)]
+pub struct Heartbeat {
+    // Deadlines are counted from the start until the first ping arrives
+    started_at: Instant,
+    last_ping: Mutex<Option<Ping>>,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self {
+            started_at: Instant::now(),
+            last_ping: Mutex::new(None),
+        }
+    }
+}
+
+impl Heartbeat {
+    /// Record a check-in, optionally reporting a failure of the job
+    pub fn ping(&self, failure: Option<String>) {
+        let ping = Ping {
+            received_at: Instant::now(),
+            failure,
+        };
+        *self.last_ping.lock().unwrap_or_else(|e| e.into_inner()) = Some(ping);
+    }
+
+    fn last_ping(&self) -> Option<Ping> {
+        self.last_ping
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// Heartbeats of all configured services by their push token, shared between
+/// the monitoring and the web server
+#[derive(Debug, Default, Clone)]
+pub struct Heartbeats {
+    heartbeats: Arc<RwLock<HashMap<String, Arc<Heartbeat>>>>,
+}
+
+impl Heartbeats {
+    pub fn register(&self, token: String, heartbeat: Arc<Heartbeat>) {
+        self.heartbeats
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(token, heartbeat);
+    }
+
+    pub fn get(&self, token: &str) -> Option<Arc<Heartbeat>> {
+        self.heartbeats
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(token)
+            .cloned()
+    }
+}
+
+/// Target of a heartbeat service in logs, notifications and errors, the
+/// token authenticates pings so only its start is shown
+pub fn heartbeat_target(token: &str) -> String {
+    let shown = (token.chars().count() / 2).min(4);
+    format!(
+        "heartbeat://{}…",
+        token.chars().take(shown).collect::<String>()
+    )
+}
+
+/// Default id of a heartbeat service, its target followed by a stable hash of
+/// the whole token so tokens sharing the shown start get distinct ids
+pub fn heartbeat_id(token: &str) -> String {
+    // 64 bit FNV-1a, unlike the std hasher it is the same across builds
+    let hash = token.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{}#{:016x}", heartbeat_target(token), hash)
+}
+
+/// Passive check of a job which has to check in periodically
+#[derive(Debug)]
+pub struct HeartbeatCheck {
+    pub token: String,
+    // Expected time between two pings
+    pub period: Duration,
+    // Additional time a late ping is tolerated
+    pub grace: Duration,
+    pub heartbeat: Arc<Heartbeat>,
+}
+
+impl HeartbeatCheck {
+    /// Evaluate the last received ping, nothing is sent over the network
+    pub fn run(&self) -> Result<(), CheckFailure> {
+        self.evaluate(Instant::now())
+    }
+
+    fn evaluate(&self, now: Instant) -> Result<(), CheckFailure> {
+        let deadline = self.period + self.grace;
+        match self.heartbeat.last_ping() {
+            Some(Ping {
+                failure: Some(reason),
+                ..
+            }) => Err(CheckFailure::ReportedFailure(reason)),
+            Some(Ping { received_at, .. }) if now.duration_since(received_at) <= deadline => Ok(()),
+            None if now.duration_since(self.heartbeat.started_at) <= deadline => Ok(()),
+            _ => Err(CheckFailure::MissedHeartbeat(deadline)),
+        }
+    }
+}
+
+impl TryFrom<&ServiceConfig> for HeartbeatCheck {
+    type Error = MonitoredServiceError;
+
+    fn try_from(service: &ServiceConfig) -> Result<Self, Self::Error> {
+        match (&service.token, service.period) {
+            (Some(token), Some(period)) if !token.is_empty() => Ok(Self {
+                token: token.clone(),
+                period,
+                grace: service.grace.unwrap_or_default(),
+                heartbeat: Arc::new(Heartbeat::default()),
+            }),
+            _ => Err(MonitoredServiceError::MissingField("token and period")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn heartbeat_check() -> HeartbeatCheck {
+        HeartbeatCheck {
+            token: "backup".to_string(),
+            period: Duration::from_secs(60),
+            grace: Duration::from_secs(30),
+            heartbeat: Arc::new(Heartbeat::default()),
+        }
+    }
+
+    #[test]
+    fn test_heartbeat_target_hides_the_token() {
+        assert_eq!(heartbeat_target("nightly-backup"), "heartbeat://nigh…");
+        assert_eq!(heartbeat_target("abc"), "heartbeat://a…");
+        assert_eq!(heartbeat_target(""), "heartbeat://…");
+    }
+
+    #[test]
+    fn test_heartbeat_id_tells_tokens_with_the_same_start_apart() {
+        let backup = heartbeat_id("nightly-backup");
+        let report = heartbeat_id("nightly-report");
+
+        assert_ne!(backup, report);
+        assert_eq!(backup, heartbeat_id("nightly-backup"));
+        assert_eq!(backup, "heartbeat://nigh…#1f1614e018ce3cdf");
+        assert!(!backup.contains("nightly-backup"), "{}", backup);
+    }
+
+    #[test]
+    fn test_missed_heartbeat() {
+        let check = heartbeat_check();
+        let started_at = check.heartbeat.started_at;
+
+        // The first deadline is counted from the start
+        assert_eq!(check.evaluate(started_at + Duration::from_secs(90)), Ok(()));
+        assert_eq!(
+            check.evaluate(started_at + Duration::from_secs(91)),
+            Err(CheckFailure::MissedHeartbeat(Duration::from_secs(90)))
+        );
+
+        check.heartbeat.ping(None);
+        let pinged_at = check.heartbeat.last_ping().unwrap().received_at;
+
+        assert_eq!(check.evaluate(pinged_at + Duration::from_secs(60)), Ok(()));
+        assert_eq!(
+            check.evaluate(pinged_at + Duration::from_secs(120)),
+            Err(CheckFailure::MissedHeartbeat(Duration::from_secs(90)))
+        );
+    }
+
+    #[test]
+    fn test_reported_failure_until_next_ping() {
+        let check = heartbeat_check();
+        let heartbeats = Heartbeats::default();
+        heartbeats.register(check.token.clone(), check.heartbeat.clone());
+
+        heartbeats
+            .get("backup")
+            .unwrap()
+            .ping(Some("disk full".to_string()));
+        assert_eq!(
+            check.run(),
+            Err(CheckFailure::ReportedFailure("disk full".to_string()))
+        );
+
+        heartbeats.get("backup").unwrap().ping(None);
+        assert_eq!(check.run(), Ok(()));
+        assert!(heartbeats.get("unknown").is_none());
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, Method, RequestBuilder, Url};
+
+use crate::assertion::{Assertion, JsonAssertion};
+use crate::model::{CheckFailure, MonitoredServiceError};
+use crate::settings::{ServiceConfig, StatusCodeRange};
+
+/// Body sent with the health check request
+#[derive(Debug, PartialEq, Clone)]
+pub enum RequestBody {
+    Raw(String),
+    Json(serde_json::Value),
+}
+
+/// Check of an HTTP endpoint
+#[derive(Debug)]
+pub struct HttpCheck {
+    pub url: String,
+    pub expected_status: Vec<StatusCodeRange>,
+    pub assertions: Vec<Assertion>,
+    pub json_assertions: Vec<JsonAssertion>,
+    pub method: Method,
+    pub headers: HeaderMap,
+    pub body: Option<RequestBody>,
+    // Whether redirects are followed, otherwise the redirect status is checked
+    pub follow_redirects: bool,
+}
+
+impl HttpCheck {
+    pub fn new(url: String) -> Result<Self, MonitoredServiceError> {
+        if !is_valid_url(&url) {
+            return Err(MonitoredServiceError::InvalidUrl(url));
+        }
+        Ok(Self {
+            url,
+            expected_status: Vec::new(),
+            assertions: Vec::new(),
+            json_assertions: Vec::new(),
+            method: Method::GET,
+            headers: HeaderMap::new(),
+            body: None,
+            follow_redirects: true,
+        })
+    }
+
+    /// Whether the response status code counts as healthy.
+    ///
+    /// Without configured expectations any 2xx code is accepted.
+    pub fn accepts_status(&self, status: u16) -> bool {
+        if self.expected_status.is_empty() {
+            return (200..=299).contains(&status);
+        }
+        self.expected_status
+            .iter()
+            .any(|range| range.contains(status))
+    }
+
+    /// Perform a single check attempt: send the request, check the response
+    /// status code and evaluate configured assertions
+    pub async fn run(
+        &self,
+        http_client: &Client,
+        timeout: Duration,
+        connect_timeout: Duration,
+    ) -> Result<(), CheckFailure> {
+        let request_failure = |error| request_failure(error, timeout, connect_timeout);
+
+        let response = self
+            .build_request(http_client, timeout)
+            .send()
+            .await
+            .map_err(request_failure)?;
+
+        let status = response.status().as_u16();
+        if !self.accepts_status(status) {
+            return Err(CheckFailure::UnexpectedStatus(status));
+        }
+
+        if self.assertions.is_empty() && self.json_assertions.is_empty() {
+            return Ok(());
+        }
+
+        let headers = response.headers().clone();
+        let body = if !self.json_assertions.is_empty()
+            || self.assertions.iter().any(Assertion::needs_body)
+        {
+            response.text().await.map_err(request_failure)?
+        } else {
+            String::new()
+        };
+
+        for assertion in &self.assertions {
+            assertion.evaluate(&headers, &body)?;
+        }
+
+        if !self.json_assertions.is_empty() {
+            let document = serde_json::from_str::<serde_json::Value>(&body)
+                .map_err(|e| CheckFailure::InvalidJson(e.to_string()))?;
+            for assertion in &self.json_assertions {
+                assertion.evaluate(&document)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn build_request(&self, http_client: &Client, timeout: Duration) -> RequestBuilder {
+        let request = http_client
+            .request(self.method.clone(), &self.url)
+            .headers(self.headers.clone())
+            .timeout(timeout);
+
+        match &self.body {
+            Some(RequestBody::Raw(body)) => request.body(body.clone()),
+            Some(RequestBody::Json(body)) => request.json(body),
+            None => request,
+        }
+    }
+}
+
+/// Translate a transport error into the reason reported in notifications
+pub(crate) fn request_failure(
+    error: reqwest::Error,
+    timeout: Duration,
+    connect_timeout: Duration,
+) -> CheckFailure {
+    if error.is_timeout() && error.is_connect() {
+        CheckFailure::ConnectTimeout(connect_timeout)
+    } else if error.is_timeout() {
+        CheckFailure::Timeout(timeout)
+    } else {
+        CheckFailure::Request(error.to_string())
+    }
+}
+
+fn is_valid_url(input: &str) -> bool {
+    Url::parse(input).is_ok()
+}
+
+pub(crate) fn build_headers(
+    headers: &BTreeMap<String, String>,
+) -> Result<HeaderMap, MonitoredServiceError> {
+    let mut header_map = HeaderMap::new();
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| MonitoredServiceError::InvalidHeader(name.clone()))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|_| MonitoredServiceError::InvalidHeader(name.to_string()))?;
+        header_map.insert(name, value);
+    }
+    Ok(header_map)
+}
+
+impl TryFrom<&ServiceConfig> for HttpCheck {
+    type Error = MonitoredServiceError;
+
+    fn try_from(service: &ServiceConfig) -> Result<Self, Self::Error> {
+        let mut check = Self::new(service.url.clone().unwrap_or_default())?;
+        check.expected_status = service.expected_status.clone();
+        check.assertions = service
+            .assertions
+            .iter()
+            .map(Assertion::try_from)
+            .collect::<Result<_, _>>()?;
+        check.json_assertions = service
+            .json_assertions
+            .iter()
+            .map(|expression| expression.parse())
+            .collect::<Result<_, _>>()
+            .map_err(MonitoredServiceError::InvalidAssertion)?;
+        check.method = service.method.into();
+        check.headers = build_headers(&service.headers)?;
+        check.body = match (&service.body, &service.json_body) {
+            (_, Some(json)) => Some(RequestBody::Json(json.clone())),
+            (Some(raw), None) => Some(RequestBody::Raw(raw.clone())),
+            (None, None) => None,
+        };
+        check.follow_redirects = service.follow_redirects.unwrap_or_else(|| {
+            !service
+                .expected_status
+                .iter()
+                .any(StatusCodeRange::is_redirect)
+        });
+
+        Ok(check)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::settings::HttpMethod;
+
+    #[test]
+    fn test_accepts_status() {
+        let mut check = HttpCheck::new("http://localhost:3000".to_string()).unwrap();
+
+        assert!(check.accepts_status(204));
+        assert!(!check.accepts_status(401));
+
+        check.expected_status = vec!["401".parse().unwrap(), "300-399".parse().unwrap()];
+
+        assert!(check.accepts_status(401));
+        assert!(check.accepts_status(301));
+        assert!(!check.accepts_status(200));
+    }
+
+    #[test]
+    fn test_request_settings_from_config() {
+        let config = ServiceConfig {
+            url: Some("http://localhost:3000".to_string()),
+            method: HttpMethod::Post,
+            headers: BTreeMap::from([("User-Agent".to_string(), "fluxa".to_string())]),
+            json_body: Some(serde_json::json!({ "probe": true })),
+            ..Default::default()
+        };
+
+        let check = HttpCheck::try_from(&config).unwrap();
+
+        assert_eq!(check.method, Method::POST);
+        assert_eq!(check.headers["user-agent"], "fluxa");
+        assert_eq!(
+            check.body,
+            Some(RequestBody::Json(serde_json::json!({ "probe": true })))
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use tokio::task;
+use tokio::time;
+
+use crate::model::{CheckFailure, MonitoredServiceError};
+use crate::settings::ServiceConfig;
+
+/// Check of free space and inodes of the filesystem a path is mounted on
+#[derive(Debug, PartialEq, Clone)]
+pub struct DiskCheck {
+    pub path: PathBuf,
+    pub min_free_percent: Option<u8>,
+    pub min_free_inodes_percent: Option<u8>,
+}
+
+impl DiskCheck {
+    pub async fn run(&self, timeout: Duration) -> Result<(), CheckFailure> {
+        let check = self.clone();
+        run_blocking(timeout, move || check.probe()).await
+    }
+
+    fn probe(&self) -> Result<(), CheckFailure> {
+        let stats = statvfs(&self.path).map_err(|e| unavailable(&self.path, e))?;
+
+        if let Some(min_free) = self.min_free_percent {
+            let free = percent(stats.f_bavail as u64, stats.f_blocks as u64);
+            self.verify("space", free, min_free)?;
+        }
+        if let Some(min_free) = self.min_free_inodes_percent {
+            // Filesystems allocating inodes dynamically report no inodes at all
+            if stats.f_files > 0 {
+                let free = percent(stats.f_favail as u64, stats.f_files as u64);
+                self.verify("inodes", free, min_free)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn verify(&self, resource: &str, free: f64, min_free: u8) -> Result<(), CheckFailure> {
+        if free < f64::from(min_free) {
+            return Err(CheckFailure::LowFreeSpace(format!(
+                "{:.1}% of {} free on {}, {}% required",
+                free,
+                resource,
+                self.path.display(),
+                min_free
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Check that a file was modified recently, e.g. a backup
+#[derive(Debug, PartialEq, Clone)]
+pub struct FileAgeCheck {
+    pub path: PathBuf,
+    pub max_age: Duration,
+}
+
+impl FileAgeCheck {
+    pub async fn run(&self, timeout: Duration) -> Result<(), CheckFailure> {
+        let check = self.clone();
+        run_blocking(timeout, move || check.probe(SystemTime::now())).await
+    }
+
+    fn probe(&self, now: SystemTime) -> Result<(), CheckFailure> {
+        let modified = fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|e| unavailable(&self.path, e))?;
+        let age = now.duration_since(modified).unwrap_or_default();
+
+        if age > self.max_age {
+            return Err(CheckFailure::StaleFile(format!(
+                "{} was modified {}s ago, at most {}s allowed",
+                self.path.display(),
+                age.as_secs(),
+                self.max_age.as_secs()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Check that a process is running, looked up in `/proc` so only on Linux
+#[derive(Debug, PartialEq, Clone)]
+pub enum ProcessCheck {
+    /// Any process with the given name
+    Name(String),
+    /// Process with the PID stored in the file
+    PidFile(PathBuf),
+}
+
+impl ProcessCheck {
+    pub async fn run(&self, timeout: Duration) -> Result<(), CheckFailure> {
+        let check = self.clone();
+        run_blocking(timeout, move || check.probe(Path::new("/proc"))).await
+    }
+
+    fn probe(&self, proc: &Path) -> Result<(), CheckFailure> {
+        match self {
+            ProcessCheck::Name(name) => {
+                let entries = fs::read_dir(proc).map_err(|e| unavailable(proc, e))?;
+                let running = entries
+                    .flatten()
+                    .filter(|entry| entry.file_name().as_bytes().iter().all(u8::is_ascii_digit))
+                    .any(|entry| process_name_matches(&entry.path(), name));
+
+                if !running {
+                    return Err(CheckFailure::ProcessNotRunning(name.clone()));
+                }
+            }
+            ProcessCheck::PidFile(pid_file) => {
+                let content = fs::read_to_string(pid_file).map_err(|e| unavailable(pid_file, e))?;
+                let pid = content.trim().parse::<u32>().map_err(|_| {
+                    CheckFailure::FileUnavailable(format!(
+                        "{}: {:?} is not a PID",
+                        pid_file.display(),
+                        content.trim()
+                    ))
+                })?;
+
+                if !proc.join(pid.to_string()).exists() {
+                    return Err(CheckFailure::ProcessNotRunning(format!(
+                        "PID {} from {}",
+                        pid,
+                        pid_file.display()
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compare the name of a `/proc/<pid>` process, the kernel keeps only the
+/// first 15 characters in `comm` so the executable of the command line is
+/// compared as well
+fn process_name_matches(process: &Path, name: &str) -> bool {
+    let comm = fs::read_to_string(process.join("comm")).unwrap_or_default();
+    if comm.trim_end() == name {
+        return true;
+    }
+
+    let cmdline = fs::read(process.join("cmdline")).unwrap_or_default();
+    let executable = cmdline.split(|byte| *byte == 0).next().unwrap_or_default();
+    Path::new(std::ffi::OsStr::from_bytes(executable))
+        .file_name()
+        .is_some_and(|file_name| file_name.as_bytes() == name.as_bytes())
+}
+
+/// Run a filesystem probe off the async runtime, a hung mount can block it indefinitely
+async fn run_blocking<F>(timeout: Duration, probe: F) -> Result<(), CheckFailure>
+where
+    F: FnOnce() -> Result<(), CheckFailure> + Send + 'static,
+{
+    match time::timeout(timeout, task::spawn_blocking(probe)).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => Err(CheckFailure::FileUnavailable(e.to_string())),
+        Err(_) => Err(CheckFailure::Timeout(timeout)),
+    }
+}
+
+fn statvfs(path: &Path) -> io::Result<libc::statvfs> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stats = MaybeUninit::<libc::statvfs>::uninit();
+
+    // SAFETY: the path is NUL terminated and the stats are read only after a successful call
+    if unsafe { libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { stats.assume_init() })
+}
+
+fn percent(free: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 100.0;
+    }
+    free as f64 * 100.0 / total as f64
+}
+
+fn unavailable(path: &Path, error: io::Error) -> CheckFailure {
+    CheckFailure::FileUnavailable(format!("{}: {}", path.display(), error))
+}
+
+impl TryFrom<&ServiceConfig> for DiskCheck {
+    type Error = MonitoredServiceError;
+
+    fn try_from(service: &ServiceConfig) -> Result<Self, Self::Error> {
+        match &service.path {
+            Some(path) => Ok(Self {
+                path: path.clone(),
+                min_free_percent: service.min_free_percent,
+                min_free_inodes_percent: service.min_free_inodes_percent,
+            }),
+            None => Err(MonitoredServiceError::MissingField("path")),
+        }
+    }
+}
+
+impl TryFrom<&ServiceConfig> for FileAgeCheck {
+    type Error = MonitoredServiceError;
+
+    fn try_from(service: &ServiceConfig) -> Result<Self, Self::Error> {
+        match (&service.path, service.max_age) {
+            (Some(path), Some(max_age)) => Ok(Self {
+                path: path.clone(),
+                max_age,
+            }),
+            _ => Err(MonitoredServiceError::MissingField("path and max_age")),
+        }
+    }
+}
+
+impl TryFrom<&ServiceConfig> for ProcessCheck {
+    type Error = MonitoredServiceError;
+
+    fn try_from(service: &ServiceConfig) -> Result<Self, Self::Error> {
+        match (&service.process_name, &service.pid_file) {
+            (Some(name), None) => Ok(Self::Name(name.clone())),
+            (None, Some(pid_file)) => Ok(Self::PidFile(pid_file.clone())),
+            _ => Err(MonitoredServiceError::MissingField(
+                "process_name or pid_file",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Write a file unique to the test into the temporary directory
+    fn temp_file(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("fluxa-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_disk_thresholds() {
+        let check = DiskCheck {
+            path: std::env::temp_dir(),
+            min_free_percent: Some(0),
+            min_free_inodes_percent: Some(0),
+        };
+        assert_eq!(check.probe(), Ok(()));
+
+        let check = DiskCheck {
+            min_free_percent: Some(101),
+            ..check
+        };
+        assert!(matches!(check.probe(), Err(CheckFailure::LowFreeSpace(_))));
+
+        let check = DiskCheck {
+            path: PathBuf::from("/nonexistent/mount"),
+            ..check
+        };
+        assert!(matches!(
+            check.probe(),
+            Err(CheckFailure::FileUnavailable(_))
+        ));
+    }
+
+    #[test]
+    fn test_file_age() {
+        let path = temp_file("backup.dump", "backup");
+        let check = FileAgeCheck {
+            path: path.clone(),
+            max_age: Duration::from_secs(3600),
+        };
+
+        assert_eq!(check.probe(SystemTime::now()), Ok(()));
+        assert_eq!(
+            check.probe(SystemTime::now() + Duration::from_secs(7200)),
+            Err(CheckFailure::StaleFile(format!(
+                "{} was modified 7200s ago, at most 3600s allowed",
+                path.display()
+            )))
+        );
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            check.probe(SystemTime::now()),
+            Err(CheckFailure::FileUnavailable(_))
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_process_by_name_and_pid_file() {
+        let timeout = Duration::from_secs(5);
+        let executable = std::env::current_exe().unwrap();
+        let name = executable.file_name().unwrap().to_str().unwrap();
+
+        assert_eq!(
+            ProcessCheck::Name(name.to_string()).run(timeout).await,
+            Ok(())
+        );
+        assert_eq!(
+            ProcessCheck::Name("fluxa-missing-daemon".to_string())
+                .run(timeout)
+                .await,
+            Err(CheckFailure::ProcessNotRunning(
+                "fluxa-missing-daemon".to_string()
+            ))
+        );
+
+        let pid_file = temp_file("daemon.pid", &format!("{}\n", std::process::id()));
+        let check = ProcessCheck::PidFile(pid_file.clone());
+        assert_eq!(check.run(timeout).await, Ok(()));
+
+        // Above the largest PID the kernel can assign
+        fs::write(&pid_file, "4194305").unwrap();
+        assert!(matches!(
+            check.run(timeout).await,
+            Err(CheckFailure::ProcessNotRunning(_))
+        ));
+        fs::remove_file(&pid_file).unwrap();
+    }
+}
@@ -0,0 +1,106 @@
+mod dns;
+mod exec;
+mod grpc;
+mod heartbeat;
+mod http;
+mod local;
+mod postgres;
+mod redis;
+mod tcp;
+mod tls;
+mod websocket;
+
+pub use dns::{parse_nameserver, DnsCheck};
+pub use exec::ExecCheck;
+pub use grpc::GrpcCheck;
+pub use heartbeat::{heartbeat_id, heartbeat_target, Heartbeat, HeartbeatCheck, Heartbeats};
+pub use http::{HttpCheck, RequestBody};
+pub use local::{DiskCheck, FileAgeCheck, ProcessCheck};
+pub use postgres::PostgresCheck;
+pub use redis::RedisCheck;
+pub(crate) use tcp::excerpt;
+pub use tcp::TcpCheck;
+pub use tls::TlsCheck;
+pub use websocket::WebSocketCheck;
+
+use std::time::Duration;
+
+use reqwest::Client;
+
+use crate::model::{CheckFailure, CheckWarning};
+
+/// What is checked and how, one variant per service `type`
+#[derive(Debug)]
+pub enum Check {
+    Http(HttpCheck),
+    Tcp(TcpCheck),
+    Dns(DnsCheck),
+    Tls(TlsCheck),
+    Heartbeat(HeartbeatCheck),
+    Exec(ExecCheck),
+    Disk(DiskCheck),
+    FileAge(FileAgeCheck),
+    Process(ProcessCheck),
+    Redis(RedisCheck),
+    Postgres(PostgresCheck),
+    WebSocket(WebSocketCheck),
+    Grpc(GrpcCheck),
+}
+
+impl Check {
+    /// Human readable identification of the checked target
+    pub fn target(&self) -> String {
+        match self {
+            Check::Http(check) => check.url.clone(),
+            Check::Tcp(check) => format!("tcp://{}:{}", check.host, check.port),
+            Check::Dns(check) => format!(
+                "dns://{}/{} ({})",
+                check.nameserver, check.domain, check.record_type
+            ),
+            Check::Tls(check) => format!("tls://{}:{}", check.host, check.port),
+            Check::Heartbeat(check) => heartbeat_target(&check.token),
+            Check::Exec(check) => format!("exec://{}", check.command),
+            Check::Disk(check) => format!("disk://{}", check.path.display()),
+            Check::FileAge(check) => format!("file://{}", check.path.display()),
+            Check::Process(ProcessCheck::Name(name)) => format!("process://{}", name),
+            Check::Process(ProcessCheck::PidFile(pid_file)) => {
+                format!("process://{}", pid_file.display())
+            }
+            Check::Redis(check) => format!("redis://{}:{}", check.host, check.port),
+            Check::Postgres(check) => format!("postgres://{}:{}", check.host, check.port),
+            Check::WebSocket(check) => check.url.to_string(),
+            Check::Grpc(check) if check.service.is_empty() => check.url.clone(),
+            Check::Grpc(check) => format!("{} ({})", check.url, check.service),
+        }
+    }
+
+    /// Perform a single check attempt, a passing check may still carry a warning
+    pub async fn run(
+        &self,
+        http_client: &Client,
+        timeout: Duration,
+        connect_timeout: Duration,
+    ) -> Result<Option<CheckWarning>, CheckFailure> {
+        match self {
+            Check::Http(check) => check
+                .run(http_client, timeout, connect_timeout)
+                .await
+                .map(|()| None),
+            Check::Tcp(check) => check.run(timeout, connect_timeout).await.map(|()| None),
+            Check::Dns(check) => check.run(timeout, connect_timeout).await.map(|()| None),
+            Check::Tls(check) => check.run(timeout, connect_timeout).await,
+            Check::Heartbeat(check) => check.run().map(|()| None),
+            Check::Exec(check) => check.run(timeout).await.map(|()| None),
+            Check::Disk(check) => check.run(timeout).await.map(|()| None),
+            Check::FileAge(check) => check.run(timeout).await.map(|()| None),
+            Check::Process(check) => check.run(timeout).await.map(|()| None),
+            Check::Redis(check) => check.run(timeout, connect_timeout).await.map(|()| None),
+            Check::Postgres(check) => check.run(timeout, connect_timeout).await.map(|()| None),
+            Check::WebSocket(check) => check.run(timeout, connect_timeout).await.map(|()| None),
+            Check::Grpc(check) => check
+                .run(http_client, timeout, connect_timeout)
+                .await
+                .map(|()| None),
+        }
+    }
+}
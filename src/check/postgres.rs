@@ -0,0 +1,254 @@
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time;
+
+use crate::check::tcp::connect;
+use crate::model::{CheckFailure, MonitoredServiceError};
+use crate::settings::ServiceConfig;
+
+/// Version 3.0 of the frontend/backend protocol
+const PROTOCOL_VERSION: i32 = 196_608;
+
+/// Maximum length of a backend message, startup replies are tiny
+const MAX_MESSAGE_SIZE: usize = 65_536;
+
+/// Check of a PostgreSQL server accepting connections.
+///
+/// The server is ready when it requests authentication or, for trusted
+/// connections, when it reports to be ready for a query.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PostgresCheck {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub database: Option<String>,
+}
+
+impl PostgresCheck {
+    /// Perform a single check attempt within the total timeout
+    pub async fn run(
+        &self,
+        timeout: Duration,
+        connect_timeout: Duration,
+    ) -> Result<(), CheckFailure> {
+        time::timeout(timeout, self.probe(connect_timeout))
+            .await
+            .map_err(|_| CheckFailure::Timeout(timeout))?
+    }
+
+    async fn probe(&self, connect_timeout: Duration) -> Result<(), CheckFailure> {
+        let mut stream = connect(&self.host, self.port, connect_timeout).await?;
+        stream
+            .write_all(&self.startup_message())
+            .await
+            .map_err(|e| CheckFailure::Connection(e.to_string()))?;
+
+        loop {
+            let (kind, body) = read_message(&mut stream).await?;
+            match kind {
+                // AuthenticationOk is followed by parameters until ReadyForQuery
+                b'R' if body.starts_with(&[0, 0, 0, 0]) => continue,
+                // Any other authentication request means the server accepts connections
+                b'R' => return Ok(()),
+                b'Z' => {
+                    // Terminate
+                    let _ = stream.write_all(&[b'X', 0, 0, 0, 4]).await;
+                    return Ok(());
+                }
+                b'E' => return Err(CheckFailure::ServerError(error_message(&body))),
+                // ParameterStatus, BackendKeyData and notices
+                b'S' | b'K' | b'N' => continue,
+                other => {
+                    return Err(CheckFailure::UnexpectedResponse(format!(
+                        "message {:?}",
+                        char::from(other)
+                    )))
+                }
+            }
+        }
+    }
+
+    fn startup_message(&self) -> Vec<u8> {
+        let mut parameters = vec![("user", self.user.as_str()), ("application_name", "fluxa")];
+        if let Some(database) = &self.database {
+            parameters.push(("database", database));
+        }
+
+        let mut body = PROTOCOL_VERSION.to_be_bytes().to_vec();
+        for (name, value) in parameters {
+            body.extend_from_slice(name.as_bytes());
+            body.push(0);
+            body.extend_from_slice(value.as_bytes());
+            body.push(0);
+        }
+        body.push(0);
+
+        let length = i32::try_from(body.len() + 4).unwrap_or(i32::MAX);
+        let mut message = length.to_be_bytes().to_vec();
+        message.extend(body);
+        message
+    }
+}
+
+/// Read a backend message type and its body
+async fn read_message(stream: &mut TcpStream) -> Result<(u8, Vec<u8>), CheckFailure> {
+    let connection_failure = |e: std::io::Error| CheckFailure::Connection(e.to_string());
+
+    let mut header = [0u8; 5];
+    stream
+        .read_exact(&mut header)
+        .await
+        .map_err(connection_failure)?;
+
+    let length = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    if !(4..=MAX_MESSAGE_SIZE).contains(&length) {
+        return Err(CheckFailure::UnexpectedResponse(format!(
+            "message length {}",
+            length
+        )));
+    }
+
+    let mut body = vec![0u8; length - 4];
+    stream
+        .read_exact(&mut body)
+        .await
+        .map_err(connection_failure)?;
+
+    Ok((header[0], body))
+}
+
+/// Human readable form of an ErrorResponse, e.g. `FATAL: the database system is starting up`
+fn error_message(body: &[u8]) -> String {
+    let mut severity = "ERROR".to_string();
+    let mut message = String::new();
+
+    for field in body
+        .split(|byte| *byte == 0)
+        .filter(|field| !field.is_empty())
+    {
+        let value = String::from_utf8_lossy(&field[1..]).to_string();
+        match field[0] {
+            b'S' => severity = value,
+            b'M' => message = value,
+            _ => {}
+        }
+    }
+
+    format!("{}: {}", severity, message)
+}
+
+impl TryFrom<&ServiceConfig> for PostgresCheck {
+    type Error = MonitoredServiceError;
+
+    fn try_from(service: &ServiceConfig) -> Result<Self, Self::Error> {
+        match &service.host {
+            Some(host) if !host.is_empty() => Ok(Self {
+                host: host.clone(),
+                port: service.port.unwrap_or(5432),
+                user: service
+                    .username
+                    .clone()
+                    .unwrap_or_else(|| "postgres".to_string()),
+                database: service.database.clone(),
+            }),
+            _ => Err(MonitoredServiceError::MissingField("host")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn message(kind: u8, body: &[u8]) -> Vec<u8> {
+        let mut message = vec![kind];
+        message.extend_from_slice(&(body.len() as u32 + 4).to_be_bytes());
+        message.extend_from_slice(body);
+        message
+    }
+
+    /// Start a stand-in server answering the startup message by the user name:
+    /// `trusted` gets in, `starting` is refused and anyone else has to send a password
+    async fn start_postgres_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut length = [0u8; 4];
+                socket.read_exact(&mut length).await.unwrap();
+                let mut startup = vec![0u8; u32::from_be_bytes(length) as usize - 4];
+                socket.read_exact(&mut startup).await.unwrap();
+                assert_eq!(startup[..4], PROTOCOL_VERSION.to_be_bytes());
+
+                let parameters = String::from_utf8(startup[4..].to_vec()).unwrap();
+                let user = parameters.split('\0').nth(1).unwrap().to_string();
+                let reply = match user.as_str() {
+                    "trusted" => [
+                        message(b'R', &[0, 0, 0, 0]),
+                        message(b'S', b"server_version\x0016.4\0"),
+                        message(b'K', &[0, 0, 0, 1, 0, 0, 0, 2]),
+                        message(b'Z', b"I"),
+                    ]
+                    .concat(),
+                    "starting" => message(
+                        b'E',
+                        b"SFATAL\0C57P03\0Mthe database system is starting up\0\0",
+                    ),
+                    // AuthenticationCleartextPassword
+                    _ => message(b'R', &[0, 0, 0, 3]),
+                };
+                socket.write_all(&reply).await.unwrap();
+            }
+        });
+        port
+    }
+
+    fn postgres_check(port: u16, user: &str) -> PostgresCheck {
+        PostgresCheck {
+            host: "127.0.0.1".to_string(),
+            port,
+            user: user.to_string(),
+            database: Some("app".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_startup_handshake() {
+        let port = start_postgres_server().await;
+        let timeout = Duration::from_secs(1);
+
+        assert_eq!(
+            postgres_check(port, "postgres").run(timeout, timeout).await,
+            Ok(())
+        );
+        assert_eq!(
+            postgres_check(port, "trusted").run(timeout, timeout).await,
+            Ok(())
+        );
+        assert_eq!(
+            postgres_check(port, "starting").run(timeout, timeout).await,
+            Err(CheckFailure::ServerError(
+                "FATAL: the database system is starting up".to_string()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_non_postgres_peer() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let _ = socket.write_all(b"SSH-2.0-OpenSSH_9.6\r\n").await;
+        });
+        let timeout = Duration::from_secs(1);
+
+        assert!(matches!(
+            postgres_check(port, "postgres").run(timeout, timeout).await,
+            Err(CheckFailure::UnexpectedResponse(_))
+        ));
+    }
+}
@@ -0,0 +1,214 @@
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time;
+
+use crate::check::tcp::{connect, excerpt};
+use crate::model::{CheckFailure, MonitoredServiceError};
+use crate::settings::ServiceConfig;
+
+/// Maximum length of a reply line
+const MAX_REPLY_SIZE: usize = 4096;
+
+/// Check of a Redis server answering `PING` with `PONG`
+#[derive(Debug, PartialEq, Clone)]
+pub struct RedisCheck {
+    pub host: String,
+    pub port: u16,
+    // ACL user, the default user is used when only the password is set
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl RedisCheck {
+    /// Perform a single check attempt within the total timeout
+    pub async fn run(
+        &self,
+        timeout: Duration,
+        connect_timeout: Duration,
+    ) -> Result<(), CheckFailure> {
+        time::timeout(timeout, self.probe(connect_timeout))
+            .await
+            .map_err(|_| CheckFailure::Timeout(timeout))?
+    }
+
+    async fn probe(&self, connect_timeout: Duration) -> Result<(), CheckFailure> {
+        let mut stream = connect(&self.host, self.port, connect_timeout).await?;
+
+        if let Some(password) = &self.password {
+            let mut command = vec!["AUTH"];
+            command.extend(self.username.as_deref());
+            command.push(password);
+            expect_reply(&mut stream, &command, "+OK").await?;
+        }
+        expect_reply(&mut stream, &["PING"], "+PONG").await?;
+
+        let _ = write_command(&mut stream, &["QUIT"]).await;
+        Ok(())
+    }
+}
+
+/// Send a command and compare the first reply line with the expected one
+async fn expect_reply(
+    stream: &mut TcpStream,
+    command: &[&str],
+    expected: &str,
+) -> Result<(), CheckFailure> {
+    write_command(stream, command).await?;
+
+    let reply = read_line(stream).await?;
+    if reply == expected.as_bytes() {
+        return Ok(());
+    }
+    match reply.strip_prefix(b"-") {
+        Some(error) => Err(CheckFailure::ServerError(excerpt(error))),
+        None => Err(CheckFailure::UnexpectedResponse(excerpt(&reply))),
+    }
+}
+
+/// Write a command as an array of bulk strings
+async fn write_command(stream: &mut TcpStream, command: &[&str]) -> Result<(), CheckFailure> {
+    let mut request = format!("*{}\r\n", command.len());
+    for argument in command {
+        request.push_str(&format!("${}\r\n{}\r\n", argument.len(), argument));
+    }
+
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| CheckFailure::Connection(e.to_string()))
+}
+
+/// Read a single reply line without the terminating CRLF
+async fn read_line(stream: &mut TcpStream) -> Result<Vec<u8>, CheckFailure> {
+    let mut line = Vec::new();
+    let mut byte = [0u8; 1];
+
+    while !line.ends_with(b"\r\n") && line.len() < MAX_REPLY_SIZE {
+        let read = stream
+            .read(&mut byte)
+            .await
+            .map_err(|e| CheckFailure::Connection(e.to_string()))?;
+        if read == 0 {
+            return Err(CheckFailure::Connection(
+                "connection closed by the server".to_string(),
+            ));
+        }
+        line.push(byte[0]);
+    }
+
+    line.truncate(line.len().saturating_sub(2));
+    Ok(line)
+}
+
+impl TryFrom<&ServiceConfig> for RedisCheck {
+    type Error = MonitoredServiceError;
+
+    fn try_from(service: &ServiceConfig) -> Result<Self, Self::Error> {
+        match &service.host {
+            Some(host) if !host.is_empty() => Ok(Self {
+                host: host.clone(),
+                port: service.port.unwrap_or(6379),
+                username: service.username.clone(),
+                password: service.password.clone(),
+            }),
+            _ => Err(MonitoredServiceError::MissingField("host")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Start a stand-in Redis server requiring the password `secret`
+    async fn start_redis_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut socket = BufReader::new(socket);
+                    let mut authenticated = false;
+                    loop {
+                        // Commands arrive as arrays of bulk strings
+                        let mut header = String::new();
+                        if socket.read_line(&mut header).await.unwrap_or(0) == 0 {
+                            break;
+                        }
+                        let count: usize = header.trim()[1..].parse().unwrap();
+                        let mut arguments = Vec::new();
+                        for _ in 0..count {
+                            let (mut length, mut argument) = (String::new(), String::new());
+                            socket.read_line(&mut length).await.unwrap();
+                            socket.read_line(&mut argument).await.unwrap();
+                            arguments.push(argument.trim().to_string());
+                        }
+
+                        let reply = match arguments[0].as_str() {
+                            "AUTH" if arguments.last().unwrap() == "secret" => {
+                                authenticated = true;
+                                "+OK\r\n"
+                            }
+                            "AUTH" => "-WRONGPASS invalid username-password pair\r\n",
+                            "PING" if authenticated => "+PONG\r\n",
+                            "PING" => "-NOAUTH Authentication required.\r\n",
+                            _ => break,
+                        };
+                        socket.get_mut().write_all(reply.as_bytes()).await.unwrap();
+                    }
+                });
+            }
+        });
+        port
+    }
+
+    fn redis_check(port: u16, password: Option<&str>) -> RedisCheck {
+        RedisCheck {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: None,
+            password: password.map(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ping_with_auth() {
+        let port = start_redis_server().await;
+        let timeout = Duration::from_secs(1);
+
+        let check = redis_check(port, Some("secret"));
+        assert_eq!(check.run(timeout, timeout).await, Ok(()));
+
+        let check = RedisCheck {
+            username: Some("monitor".to_string()),
+            ..check
+        };
+        assert_eq!(check.run(timeout, timeout).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_error_replies() {
+        let port = start_redis_server().await;
+        let timeout = Duration::from_secs(1);
+
+        let check = redis_check(port, None);
+        assert_eq!(
+            check.run(timeout, timeout).await,
+            Err(CheckFailure::ServerError(
+                "NOAUTH Authentication required.".to_string()
+            ))
+        );
+
+        let check = redis_check(port, Some("wrong"));
+        assert_eq!(
+            check.run(timeout, timeout).await,
+            Err(CheckFailure::ServerError(
+                "WRONGPASS invalid username-password pair".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time;
+
+use crate::model::{CheckFailure, MonitoredServiceError};
+use crate::settings::ServiceConfig;
+
+/// Maximum number of bytes read while waiting for the expected response
+const MAX_RESPONSE_SIZE: usize = 4096;
+
+/// Check of a raw TCP port
+#[derive(Debug, PartialEq, Clone)]
+pub struct TcpCheck {
+    pub host: String,
+    pub port: u16,
+    // Payload written right after the connection is established
+    pub send: Option<String>,
+    // Text the peer has to respond with, e.g. a banner
+    pub expect: Option<String>,
+}
+
+impl TcpCheck {
+    /// Perform a single check attempt within the total timeout
+    pub async fn run(
+        &self,
+        timeout: Duration,
+        connect_timeout: Duration,
+    ) -> Result<(), CheckFailure> {
+        time::timeout(timeout, self.probe(connect_timeout))
+            .await
+            .map_err(|_| CheckFailure::Timeout(timeout))?
+    }
+
+    async fn probe(&self, connect_timeout: Duration) -> Result<(), CheckFailure> {
+        let mut stream = connect(&self.host, self.port, connect_timeout).await?;
+
+        if let Some(payload) = &self.send {
+            stream
+                .write_all(payload.as_bytes())
+                .await
+                .map_err(|e| CheckFailure::Connection(e.to_string()))?;
+        }
+
+        if let Some(expected) = &self.expect {
+            let response = read_until(&mut stream, expected.as_bytes()).await?;
+            if !contains(&response, expected.as_bytes()) {
+                return Err(CheckFailure::UnexpectedResponse(excerpt(&response)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Open a TCP connection within the connection timeout
+pub(crate) async fn connect(
+    host: &str,
+    port: u16,
+    connect_timeout: Duration,
+) -> Result<TcpStream, CheckFailure> {
+    time::timeout(connect_timeout, TcpStream::connect((host, port)))
+        .await
+        .map_err(|_| CheckFailure::ConnectTimeout(connect_timeout))?
+        .map_err(|e| CheckFailure::Connection(e.to_string()))
+}
+
+/// Read from the stream until the expected bytes arrive, the peer closes the
+/// connection or the response grows over the limit
+async fn read_until(stream: &mut TcpStream, expected: &[u8]) -> Result<Vec<u8>, CheckFailure> {
+    let mut response = Vec::new();
+    let mut buffer = [0u8; 512];
+
+    while !contains(&response, expected) && response.len() < MAX_RESPONSE_SIZE {
+        let read = stream
+            .read(&mut buffer)
+            .await
+            .map_err(|e| CheckFailure::Connection(e.to_string()))?;
+        if read == 0 {
+            break;
+        }
+        response.extend_from_slice(&buffer[..read]);
+    }
+
+    Ok(response)
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    needle.is_empty()
+        || haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+}
+
+/// Shortened printable form of a response for notifications
+pub(crate) fn excerpt(response: &[u8]) -> String {
+    let text = String::from_utf8_lossy(response);
+    let text = text.trim();
+    match text.char_indices().nth(100) {
+        Some((index, _)) => format!("{}...", &text[..index]),
+        None => text.to_string(),
+    }
+}
+
+impl TryFrom<&ServiceConfig> for TcpCheck {
+    type Error = MonitoredServiceError;
+
+    fn try_from(service: &ServiceConfig) -> Result<Self, Self::Error> {
+        match (&service.host, service.port) {
+            (Some(host), Some(port)) if !host.is_empty() => Ok(Self {
+                host: host.clone(),
+                port,
+                send: service.send.clone(),
+                expect: service.expect.clone(),
+            }),
+            _ => Err(MonitoredServiceError::MissingField("host and port")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Start a stand-in server answering `+PONG` to `PING`, like Redis does
+    async fn start_ping_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buffer = [0u8; 64];
+                if let Ok(read) = socket.read(&mut buffer).await {
+                    if buffer[..read].starts_with(b"PING") {
+                        let _ = socket.write_all(b"+PONG\r\n").await;
+                    }
+                }
+            }
+        });
+        port
+    }
+
+    fn tcp_check(port: u16, send: Option<&str>, expect: Option<&str>) -> TcpCheck {
+        TcpCheck {
+            host: "127.0.0.1".to_string(),
+            port,
+            send: send.map(str::to_string),
+            expect: expect.map(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_payload_and_expected_response() {
+        let port = start_ping_server().await;
+        let timeout = Duration::from_secs(1);
+
+        let check = tcp_check(port, Some("PING\r\n"), Some("+PONG"));
+        assert_eq!(check.run(timeout, timeout).await, Ok(()));
+
+        let check = tcp_check(port, Some("QUIT\r\n"), Some("+PONG"));
+        assert_eq!(
+            check.run(timeout, timeout).await,
+            Err(CheckFailure::UnexpectedResponse(String::new()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_closed_port_and_silent_peer() {
+        let port = start_ping_server().await;
+        let timeout = Duration::from_millis(200);
+
+        // The server waits for a payload, so the banner never arrives
+        let check = tcp_check(port, None, Some("SSH-"));
+        assert_eq!(
+            check.run(timeout, timeout).await,
+            Err(CheckFailure::Timeout(timeout))
+        );
+
+        let closed_port = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let check = tcp_check(closed_port, None, None);
+        assert!(matches!(
+            check.run(timeout, timeout).await,
+            Err(CheckFailure::Connection(_))
+        ));
+    }
+}
@@ -0,0 +1,376 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use tokio::time;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName};
+use tokio_rustls::rustls::{CertificateError, ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+use crate::check::tcp::connect;
+use crate::model::{CheckFailure, CheckWarning, MonitoredServiceError};
+use crate::settings::{ServiceConfig, DEFAULT_EXPIRY_WARNING_DAYS};
+
+/// Check of the TLS certificate presented by a server
+#[derive(Debug)]
+pub struct TlsCheck {
+    pub host: String,
+    pub port: u16,
+    // Name the certificate has to be valid for
+    pub server_name: ServerName<'static>,
+    // Days before expiry when a warning is sent, sorted from the closest one
+    pub expiry_warning_days: Vec<u32>,
+    pub tls_config: Arc<ClientConfig>,
+}
+
+impl TlsCheck {
+    /// Perform a single check attempt within the total timeout.
+    ///
+    /// A valid certificate expiring within one of the warning tiers is
+    /// reported as a warning, not as a failure.
+    pub async fn run(
+        &self,
+        timeout: Duration,
+        connect_timeout: Duration,
+    ) -> Result<Option<CheckWarning>, CheckFailure> {
+        time::timeout(timeout, self.probe(connect_timeout))
+            .await
+            .map_err(|_| CheckFailure::Timeout(timeout))?
+    }
+
+    async fn probe(&self, connect_timeout: Duration) -> Result<Option<CheckWarning>, CheckFailure> {
+        let stream = connect(&self.host, self.port, connect_timeout).await?;
+        let stream = TlsConnector::from(self.tls_config.clone())
+            .connect(self.server_name.clone(), stream)
+            .await
+            .map_err(|e| self.handshake_failure(e))?;
+
+        let expires_at = stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|certificate| not_after(certificate))
+            .min()
+            .ok_or_else(|| CheckFailure::Tls("no readable peer certificate".to_string()))?;
+
+        Ok(self.expiry_warning(expires_at, Utc::now()))
+    }
+
+    /// Warning for the closest tier the expiry date falls into
+    fn expiry_warning(
+        &self,
+        expires_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Option<CheckWarning> {
+        let remaining = expires_at - now;
+        self.expiry_warning_days
+            .iter()
+            .find(|days| remaining <= chrono::Duration::days(i64::from(**days)))
+            .map(|tier| CheckWarning::CertificateExpiring {
+                days_left: remaining.num_days(),
+                expires_at,
+                tier: *tier,
+            })
+    }
+
+    fn handshake_failure(&self, error: std::io::Error) -> CheckFailure {
+        let Some(tls_error) = error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<tokio_rustls::rustls::Error>())
+        else {
+            return CheckFailure::Connection(error.to_string());
+        };
+
+        // Newer rustls releases report the `*Context` variants with details
+        let reason = match tls_error {
+            tokio_rustls::rustls::Error::InvalidCertificate(
+                CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. },
+            ) => format!("certificate is not valid for {}", self.server_name.to_str()),
+            tokio_rustls::rustls::Error::InvalidCertificate(CertificateError::UnknownIssuer) => {
+                "certificate chain is not trusted".to_string()
+            }
+            tokio_rustls::rustls::Error::InvalidCertificate(
+                CertificateError::Expired | CertificateError::ExpiredContext { .. },
+            ) => "certificate has expired".to_string(),
+            other => other.to_string(),
+        };
+        CheckFailure::Tls(reason)
+    }
+}
+
+/// Client configuration trusting the bundled web roots and the given certificates
+pub(crate) fn client_config(
+    extra_roots: Vec<CertificateDer<'static>>,
+) -> Result<Arc<ClientConfig>, MonitoredServiceError> {
+    let mut roots = RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    for certificate in extra_roots {
+        roots
+            .add(certificate)
+            .map_err(|e| MonitoredServiceError::InvalidCaFile(e.to_string()))?;
+    }
+
+    let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| MonitoredServiceError::InvalidCaFile(e.to_string()))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    Ok(Arc::new(config))
+}
+
+fn load_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>, MonitoredServiceError> {
+    let invalid = |e: &dyn std::fmt::Display| {
+        MonitoredServiceError::InvalidCaFile(format!("{}: {}", path.display(), e))
+    };
+
+    let certificates = CertificateDer::pem_file_iter(path)
+        .map_err(|e| invalid(&e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| invalid(&e))?;
+    if certificates.is_empty() {
+        return Err(invalid(&"no certificates found"));
+    }
+
+    Ok(certificates)
+}
+
+/// Read the end of the validity period from a DER encoded X.509 certificate
+fn not_after(certificate: &[u8]) -> Option<DateTime<Utc>> {
+    let (_, certificate, _) = read_element(certificate)?;
+    let (_, tbs_certificate, _) = read_element(certificate)?;
+
+    let mut fields = tbs_certificate;
+    let (tag, _, rest) = read_element(fields)?;
+    // Skip the explicitly tagged version, it is missing in v1 certificates
+    if tag == 0xa0 {
+        fields = rest;
+    }
+    // Serial number, signature algorithm and issuer precede the validity
+    for _ in 0..3 {
+        fields = read_element(fields)?.2;
+    }
+
+    let (_, validity, _) = read_element(fields)?;
+    let (_, _, validity) = read_element(validity)?;
+    let (tag, not_after, _) = read_element(validity)?;
+    let not_after = std::str::from_utf8(not_after).ok()?;
+
+    let not_after = match tag {
+        // UTCTime with a two digit year, RFC 5280 maps 50-99 to the 20th century
+        0x17 => {
+            let century = if not_after.get(..2)? >= "50" {
+                "19"
+            } else {
+                "20"
+            };
+            format!("{}{}", century, not_after)
+        }
+        // GeneralizedTime
+        0x18 => not_after.to_string(),
+        _ => return None,
+    };
+
+    NaiveDateTime::parse_from_str(&not_after, "%Y%m%d%H%M%SZ")
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+/// Split a DER element into its tag, content and the following bytes
+fn read_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&length, mut input) = input.split_first()?;
+
+    let length = if length & 0x80 == 0 {
+        usize::from(length)
+    } else {
+        let octets = usize::from(length & 0x7f);
+        if octets == 0 || octets > 4 || input.len() < octets {
+            return None;
+        }
+        let (length, rest) = input.split_at(octets);
+        input = rest;
+        length
+            .iter()
+            .fold(0usize, |length, octet| length << 8 | usize::from(*octet))
+    };
+
+    if input.len() < length {
+        return None;
+    }
+    let (content, rest) = input.split_at(length);
+    Some((tag, content, rest))
+}
+
+impl TryFrom<&ServiceConfig> for TlsCheck {
+    type Error = MonitoredServiceError;
+
+    fn try_from(service: &ServiceConfig) -> Result<Self, Self::Error> {
+        let host = match &service.host {
+            Some(host) if !host.is_empty() => host.clone(),
+            _ => return Err(MonitoredServiceError::MissingField("host")),
+        };
+        let name = service.server_name.clone().unwrap_or_else(|| host.clone());
+        let server_name = ServerName::try_from(name.clone())
+            .map_err(|_| MonitoredServiceError::InvalidAddress(name))?;
+
+        let mut expiry_warning_days = service
+            .expiry_warning_days
+            .clone()
+            .unwrap_or_else(|| DEFAULT_EXPIRY_WARNING_DAYS.to_vec());
+        expiry_warning_days.sort_unstable();
+        expiry_warning_days.dedup();
+
+        let extra_roots = match &service.ca_file {
+            Some(path) => load_certificates(path)?,
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            host,
+            port: service.port.unwrap_or(443),
+            server_name,
+            expiry_warning_days,
+            tls_config: client_config(extra_roots)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use tokio::net::TcpListener;
+    use tokio_rustls::rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+    use tokio_rustls::rustls::ServerConfig;
+    use tokio_rustls::TlsAcceptor;
+
+    /// Issue a certificate for `localhost` expiring in `days` and serve it with
+    /// its CA, returns the port and the CA certificate
+    async fn start_tls_server(days: i64) -> (u16, CertificateDer<'static>) {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+        let expiry = (Utc::now() + chrono::Duration::days(days)).date_naive();
+        params.not_after = rcgen::date_time_ymd(
+            expiry.format("%Y").to_string().parse().unwrap(),
+            expiry.format("%m").to_string().parse().unwrap(),
+            expiry.format("%d").to_string().parse().unwrap(),
+        );
+        let leaf = params.signed_by(&key, &ca, &ca_key).unwrap();
+
+        let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![leaf.der().clone(), ca.der().clone()],
+                PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der())),
+            )
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let _ = acceptor.accept(socket).await;
+                });
+            }
+        });
+
+        (port, ca.der().clone())
+    }
+
+    fn tls_check(port: u16, server_name: &str, roots: Vec<CertificateDer<'static>>) -> TlsCheck {
+        TlsCheck {
+            host: "127.0.0.1".to_string(),
+            port,
+            server_name: ServerName::try_from(server_name.to_string()).unwrap(),
+            expiry_warning_days: vec![1, 7, 21],
+            tls_config: client_config(roots).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expiring_certificate_is_reported_as_warning() {
+        let (port, ca) = start_tls_server(5).await;
+        let timeout = Duration::from_secs(2);
+
+        let warning = tls_check(port, "localhost", vec![ca])
+            .run(timeout, timeout)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            warning,
+            Some(CheckWarning::CertificateExpiring { tier: 7, days_left, .. }) if days_left == 4
+        ));
+
+        let (port, ca) = start_tls_server(90).await;
+        let check = tls_check(port, "localhost", vec![ca]);
+        assert_eq!(check.run(timeout, timeout).await, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn test_name_mismatch_and_untrusted_chain() {
+        let (port, ca) = start_tls_server(90).await;
+        let timeout = Duration::from_secs(2);
+
+        let check = tls_check(port, "example.com", vec![ca]);
+        assert_eq!(
+            check.run(timeout, timeout).await,
+            Err(CheckFailure::Tls(
+                "certificate is not valid for example.com".to_string()
+            ))
+        );
+
+        let check = tls_check(port, "localhost", Vec::new());
+        assert_eq!(
+            check.run(timeout, timeout).await,
+            Err(CheckFailure::Tls(
+                "certificate chain is not trusted".to_string()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_certificate() {
+        let (port, ca) = start_tls_server(-5).await;
+        let timeout = Duration::from_secs(2);
+
+        let check = tls_check(port, "localhost", vec![ca]);
+        assert_eq!(
+            check.run(timeout, timeout).await,
+            Err(CheckFailure::Tls("certificate has expired".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_expiry_warning_tiers() {
+        let check = tls_check(443, "localhost", Vec::new());
+        let now = Utc::now();
+        let tier = |days: i64| {
+            check
+                .expiry_warning(now + chrono::Duration::days(days), now)
+                .as_ref()
+                .map(CheckWarning::level)
+        };
+
+        assert_eq!(tier(30), None);
+        assert_eq!(tier(21), Some(21));
+        assert_eq!(tier(8), Some(21));
+        assert_eq!(tier(7), Some(7));
+        assert_eq!(tier(0), Some(1));
+    }
+}
@@ -0,0 +1,232 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use reqwest::header::HeaderMap;
+use reqwest::Url;
+use tokio::time;
+use tokio_rustls::rustls::ClientConfig;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_tungstenite::{client_async_tls_with_config, Connector};
+use url::Host;
+
+use crate::check::tcp::{connect, excerpt};
+use crate::check::tls::client_config;
+use crate::model::{CheckFailure, MonitoredServiceError};
+use crate::settings::ServiceConfig;
+
+/// Check of a WebSocket endpoint completing the upgrade handshake
+#[derive(Debug)]
+pub struct WebSocketCheck {
+    pub url: Url,
+    // Additional headers of the upgrade request, e.g. Authorization
+    pub headers: HeaderMap,
+    // Text message sent right after the handshake
+    pub send: Option<String>,
+    // Text a received message has to contain
+    pub expect: Option<String>,
+    pub tls_config: Arc<ClientConfig>,
+}
+
+impl WebSocketCheck {
+    /// Perform a single check attempt within the total timeout
+    pub async fn run(
+        &self,
+        timeout: Duration,
+        connect_timeout: Duration,
+    ) -> Result<(), CheckFailure> {
+        time::timeout(timeout, self.probe(connect_timeout))
+            .await
+            .map_err(|_| CheckFailure::Timeout(timeout))?
+    }
+
+    async fn probe(&self, connect_timeout: Duration) -> Result<(), CheckFailure> {
+        // An IPv6 address is bracketed in the url but not when connecting
+        let host = match self.url.host() {
+            Some(Host::Ipv6(address)) => address.to_string(),
+            _ => self.url.host_str().unwrap_or_default().to_string(),
+        };
+        let port = self.url.port_or_known_default().unwrap_or_default();
+        let stream = connect(&host, port, connect_timeout).await?;
+
+        let mut request = self
+            .url
+            .as_str()
+            .into_client_request()
+            .map_err(|e| CheckFailure::Request(e.to_string()))?;
+        request.headers_mut().extend(self.headers.clone());
+
+        let connector = Connector::Rustls(self.tls_config.clone());
+        let (mut socket, _) = client_async_tls_with_config(request, stream, None, Some(connector))
+            .await
+            .map_err(handshake_failure)?;
+
+        if let Some(payload) = &self.send {
+            socket
+                .send(Message::text(payload.as_str()))
+                .await
+                .map_err(|e| CheckFailure::Connection(e.to_string()))?;
+        }
+
+        if let Some(expected) = &self.expect {
+            let mut last_message = String::new();
+            loop {
+                match socket.next().await {
+                    Some(Ok(Message::Text(text))) => last_message = text.to_string(),
+                    Some(Ok(Message::Binary(data))) => last_message = excerpt(&data),
+                    // Control frames are answered by the socket itself
+                    Some(Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_))) => continue,
+                    Some(Ok(Message::Close(_))) | None => {
+                        return Err(CheckFailure::UnexpectedResponse(excerpt(
+                            last_message.as_bytes(),
+                        )))
+                    }
+                    Some(Err(e)) => return Err(CheckFailure::Connection(e.to_string())),
+                }
+                if last_message.contains(expected.as_str()) {
+                    break;
+                }
+            }
+        }
+
+        let _ = socket.close(None).await;
+        Ok(())
+    }
+}
+
+/// Translate a failed upgrade into the reason reported in notifications
+fn handshake_failure(error: WsError) -> CheckFailure {
+    match error {
+        WsError::Http(response) => CheckFailure::UnexpectedStatus(response.status().as_u16()),
+        WsError::Tls(e) => CheckFailure::Tls(e.to_string()),
+        WsError::Io(e) => CheckFailure::Connection(e.to_string()),
+        other => CheckFailure::Request(other.to_string()),
+    }
+}
+
+impl TryFrom<&ServiceConfig> for WebSocketCheck {
+    type Error = MonitoredServiceError;
+
+    fn try_from(service: &ServiceConfig) -> Result<Self, Self::Error> {
+        let url = service.url.clone().unwrap_or_default();
+        let url = match Url::parse(&url) {
+            Ok(parsed) if matches!(parsed.scheme(), "ws" | "wss") && parsed.has_host() => parsed,
+            _ => return Err(MonitoredServiceError::InvalidUrl(url)),
+        };
+
+        Ok(Self {
+            url,
+            headers: super::http::build_headers(&service.headers)?,
+            send: service.send.clone(),
+            expect: service.expect.clone(),
+            tls_config: client_config(Vec::new())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_hdr_async;
+    use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+
+    /// Refuse the upgrade without the `Authorization` header, the callback
+    /// signature is given by tungstenite
+    #[allow(clippy::result_large_err)]
+    fn authorize(request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+        if request.headers().contains_key("authorization") {
+            return Ok(response);
+        }
+        let mut refused = ErrorResponse::new(None);
+        *refused.status_mut() = reqwest::StatusCode::UNAUTHORIZED;
+        Err(refused)
+    }
+
+    /// Start a stand-in server echoing messages upper-cased
+    async fn start_websocket_server() -> String {
+        start_websocket_server_on("127.0.0.1:0").await
+    }
+
+    async fn start_websocket_server_on(address: &str) -> String {
+        let listener = TcpListener::bind(address).await.unwrap();
+        let url = format!("ws://{}/events", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let Ok(mut socket) = accept_hdr_async(stream, authorize).await else {
+                        return;
+                    };
+                    while let Some(Ok(Message::Text(text))) = socket.next().await {
+                        if text.as_str() != "silence" {
+                            let reply = Message::text(text.to_uppercase());
+                            let _ = socket.send(reply).await;
+                        }
+                    }
+                });
+            }
+        });
+        url
+    }
+
+    fn websocket_check(url: &str, send: Option<&str>, expect: Option<&str>) -> WebSocketCheck {
+        let config = ServiceConfig {
+            url: Some(url.to_string()),
+            headers: [("Authorization".to_string(), "Bearer token".to_string())].into(),
+            send: send.map(str::to_string),
+            expect: expect.map(str::to_string),
+            ..Default::default()
+        };
+        WebSocketCheck::try_from(&config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_handshake_and_expected_reply() {
+        let url = start_websocket_server().await;
+        let timeout = Duration::from_secs(1);
+
+        let check = websocket_check(&url, None, None);
+        assert_eq!(check.run(timeout, timeout).await, Ok(()));
+
+        let check = websocket_check(&url, Some("ping"), Some("PING"));
+        assert_eq!(check.run(timeout, timeout).await, Ok(()));
+
+        let check = WebSocketCheck {
+            headers: HeaderMap::new(),
+            ..check
+        };
+        assert_eq!(
+            check.run(timeout, timeout).await,
+            Err(CheckFailure::UnexpectedStatus(401))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ipv6_address() {
+        let url = start_websocket_server_on("[::1]:0").await;
+        assert!(url.starts_with("ws://[::1]:"), "{}", url);
+        let timeout = Duration::from_secs(1);
+
+        let check = websocket_check(&url, Some("ping"), Some("PING"));
+        assert_eq!(check.run(timeout, timeout).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_missing_reply_times_out() {
+        let url = start_websocket_server().await;
+        let timeout = Duration::from_millis(200);
+
+        let check = websocket_check(&url, Some("silence"), Some("pong"));
+        assert_eq!(
+            check.run(timeout, timeout).await,
+            Err(CheckFailure::Timeout(timeout))
+        );
+
+        let config = ServiceConfig {
+            url: Some(url.replace("ws://", "http://")),
+            ..Default::default()
+        };
+        assert!(WebSocketCheck::try_from(&config).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::de::{self, Deserializer, Visitor};
+
+/// Longest accepted duration, anything longer is most likely a typo
+pub const MAX_DURATION: Duration = Duration::from_secs(366 * 24 * 3600);
+
+/// Parse a duration like `30s`, `5m`, `1h30m` or `500ms`, units `ms`, `s`,
+/// `m`, `h` and `d` can be combined from the largest to the smallest
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration {:?}, e.g. \"30s\" or \"1h30m\"", input);
+
+    let mut rest = input.trim();
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut total = Duration::ZERO;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let value: u64 = rest[..digits].parse().map_err(|_| invalid())?;
+        rest = &rest[digits..];
+
+        let unit_length = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let seconds = |factor: u64| value.checked_mul(factor).map(Duration::from_secs);
+        let part = match &rest[..unit_length] {
+            "ms" => Some(Duration::from_millis(value)),
+            "s" => seconds(1),
+            "m" => seconds(60),
+            "h" => seconds(3600),
+            "d" => seconds(86400),
+            _ => return Err(invalid()),
+        };
+        rest = &rest[unit_length..];
+
+        total = part
+            .and_then(|part| total.checked_add(part))
+            .filter(|total| *total <= MAX_DURATION)
+            .ok_or_else(|| format!("duration {:?} is longer than a year", input))?;
+    }
+
+    Ok(total)
+}
+
+/// Deserialize a duration given as integer seconds or as a duration string
+pub fn seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    deserializer.deserialize_any(DurationVisitor {
+        unit: Duration::from_secs,
+    })
+}
+
+pub fn optional_seconds<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    seconds(deserializer).map(Some)
+}
+
+/// Deserialize a duration given as integer milliseconds or as a duration string
+pub fn optional_milliseconds<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    deserializer
+        .deserialize_any(DurationVisitor {
+            unit: Duration::from_millis,
+        })
+        .map(Some)
+}
+
+struct DurationVisitor {
+    // Unit of durations given as integers
+    unit: fn(u64) -> Duration,
+}
+
+impl Visitor<'_> for DurationVisitor {
+    type Value = Duration;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a non-negative integer or a duration like \"30s\" or \"1h30m\"")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Duration, E> {
+        let duration = (self.unit)(value);
+        if duration > MAX_DURATION {
+            return Err(E::custom(format!(
+                "duration {} is longer than a year",
+                value
+            )));
+        }
+        Ok(duration)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Duration, E> {
+        match u64::try_from(value) {
+            Ok(value) => self.visit_u64(value),
+            Err(_) => Err(E::custom(format!("duration {} is negative", value))),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Duration, E> {
+        parse_duration(value).map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1d2h"), Ok(Duration::from_secs(93600)));
+        assert_eq!(parse_duration(" 2s "), Ok(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_invalid_durations() {
+        for input in ["", "30", "s", "1.5s", "5 min", "-3s", "10y"] {
+            assert!(parse_duration(input).is_err(), "{:?}", input);
+        }
+        assert_eq!(
+            parse_duration("400d"),
+            Err("duration \"400d\" is longer than a year".to_string())
+        );
+        assert!(parse_duration("99999999999999999999h").is_err());
+    }
+}
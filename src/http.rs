@@ -1,21 +1,31 @@
-use axum::{routing::get, Router};
-use log::info;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::get,
+    Router,
+};
+use log::{debug, info};
 use std::net::SocketAddr;
 use std::str::FromStr;
 
+use crate::check::{heartbeat_target, Heartbeats};
 use crate::error::HttpError;
 
 pub struct WebServer {
     listen_address: String,
+    heartbeats: Heartbeats,
 }
 
 impl WebServer {
-    pub fn new(listen_address: String) -> Self {
-        Self { listen_address }
+    pub fn new(listen_address: String, heartbeats: Heartbeats) -> Self {
+        Self {
+            listen_address,
+            heartbeats,
+        }
     }
 
     pub async fn run(self) -> Result<(), HttpError> {
-        let app = Router::new().route("/", get(|| async { "OK" }));
+        let app = router(self.heartbeats);
 
         let addr = SocketAddr::from_str(self.listen_address.as_str())?;
 
@@ -34,3 +44,96 @@ impl WebServer {
         }
     }
 }
+
+fn router(heartbeats: Heartbeats) -> Router {
+    Router::new()
+        .route("/", get(|| async { "OK" }))
+        .route("/push/{token}", get(push).post(push))
+        .route("/push/{token}/fail", get(push_failure).post(push_failure))
+        .with_state(heartbeats)
+}
+
+/// Record a successful check-in of a heartbeat service
+async fn push(State(heartbeats): State<Heartbeats>, Path(token): Path<String>) -> StatusCode {
+    match heartbeats.get(&token) {
+        Some(heartbeat) => {
+            debug!("Heartbeat received for {}", heartbeat_target(&token));
+            heartbeat.ping(None);
+            StatusCode::OK
+        }
+        None => StatusCode::NOT_FOUND,
+    }
+}
+
+/// Record a failure reported by a heartbeat service, the request body is used as the reason
+async fn push_failure(
+    State(heartbeats): State<Heartbeats>,
+    Path(token): Path<String>,
+    body: String,
+) -> StatusCode {
+    match heartbeats.get(&token) {
+        Some(heartbeat) => {
+            debug!("Failure reported for {}", heartbeat_target(&token));
+            let reason = match body.trim() {
+                "" => "no details".to_string(),
+                reason => crate::check::excerpt(reason.as_bytes()),
+            };
+            heartbeat.ping(Some(reason));
+            StatusCode::OK
+        }
+        None => StatusCode::NOT_FOUND,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::check::{Heartbeat, HeartbeatCheck};
+    use crate::model::CheckFailure;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_push_endpoints() {
+        let check = HeartbeatCheck {
+            token: "backup".to_string(),
+            period: Duration::from_secs(60),
+            grace: Duration::ZERO,
+            heartbeat: Arc::new(Heartbeat::default()),
+        };
+        let heartbeats = Heartbeats::default();
+        heartbeats.register(check.token.clone(), check.heartbeat.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router(heartbeats)).await });
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!("{}/push/backup/fail", url))
+            .body("disk full\n")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            check.run(),
+            Err(CheckFailure::ReportedFailure("disk full".to_string()))
+        );
+
+        let response = client
+            .get(format!("{}/push/backup", url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(check.run(), Ok(()));
+
+        let response = client
+            .get(format!("{}/push/unknown", url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,6 +1,11 @@
+pub mod assertion;
+pub mod check;
+pub mod duration;
 pub mod error;
 pub mod http;
+pub mod maintenance;
 pub mod model;
 pub mod monitoring;
 pub mod notification;
+pub mod secret;
 pub mod settings;
@@ -3,7 +3,10 @@ use std::path::PathBuf;
 use clap::{builder::PathBufValueParser, Arg, Command};
 use fluxa::http::WebServer;
 use fluxa::{
-    error::FluxaError, monitoring::MonitoringService, notification::NotificationManager,
+    check::Heartbeats,
+    error::{FluxaError, ServiceError},
+    monitoring::MonitoringService,
+    notification::NotificationManager,
     settings::FluxaConfig,
 };
 use log::info;
@@ -19,7 +22,7 @@ async fn main() -> Result<(), FluxaError> {
             Arg::new("config")
                 .short('c')
                 .long("config")
-                .help("Path to configuration file")
+                .help("Path to configuration file or directory")
                 .value_parser(PathBufValueParser::default()),
         )
         .get_matches();
@@ -29,14 +32,23 @@ async fn main() -> Result<(), FluxaError> {
 
     let conf = FluxaConfig::new(config_path.as_path())?;
 
-    let http_client = std::sync::Arc::new(reqwest::Client::new());
+    let http_client = std::sync::Arc::new(
+        reqwest::Client::builder()
+            .timeout(conf.fluxa.timeout)
+            .connect_timeout(conf.fluxa.connect_timeout)
+            .build()
+            .map_err(ServiceError::HttpRequest)?,
+    );
     let notification_manager =
         std::sync::Arc::new(NotificationManager::from_config(&conf, http_client.clone()));
 
+    // Pings of heartbeat services are received by the web server
+    let heartbeats = Heartbeats::default();
+
     let monitoring_service =
-        MonitoringService::new(http_client, notification_manager, conf.services)?;
+        MonitoringService::new(notification_manager, conf.services, &heartbeats)?;
 
-    let web_server = WebServer::new(conf.fluxa.listen);
+    let web_server = WebServer::new(conf.fluxa.listen, heartbeats);
 
     info!("🚀 Starting Fluxa with monitoring + web server");
 
@@ -0,0 +1,334 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveTime, TimeDelta, Utc, Weekday};
+use chrono_tz::Tz;
+use cron::Schedule;
+
+use crate::settings::MaintenanceConfig;
+
+/// Period when status changes of a service are not notified, the service
+/// is still checked
+#[derive(Debug, Clone)]
+pub enum MaintenanceWindow {
+    /// Single window between two timestamps
+    OneOff {
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+    },
+    /// Window starting at every occurrence of a cron schedule
+    Cron {
+        schedule: Box<Schedule>,
+        duration: Duration,
+        timezone: Tz,
+    },
+    /// Window on the given weekdays between two local times, it continues
+    /// into the next day when `to` is before `from`
+    Weekly {
+        weekdays: Vec<Weekday>,
+        from: NaiveTime,
+        to: NaiveTime,
+        timezone: Tz,
+    },
+}
+
+impl MaintenanceWindow {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        match self {
+            MaintenanceWindow::OneOff { start, end } => *start <= now && now < *end,
+            MaintenanceWindow::Cron {
+                schedule,
+                duration,
+                timezone,
+            } => {
+                // The window is open when the schedule fired within its duration
+                let Ok(duration) = TimeDelta::from_std(*duration) else {
+                    return false;
+                };
+                let since = (now - duration).with_timezone(timezone);
+                schedule
+                    .after(&since)
+                    .next()
+                    .is_some_and(|start| start <= now)
+            }
+            MaintenanceWindow::Weekly {
+                weekdays,
+                from,
+                to,
+                timezone,
+            } => {
+                let local = now.with_timezone(timezone);
+                let (day, time) = (local.weekday(), local.time());
+
+                if from <= to {
+                    weekdays.contains(&day) && *from <= time && time < *to
+                } else {
+                    (weekdays.contains(&day) && *from <= time)
+                        || (weekdays.contains(&day.pred()) && time < *to)
+                }
+            }
+        }
+    }
+}
+
+impl TryFrom<&MaintenanceConfig> for MaintenanceWindow {
+    type Error = String;
+
+    fn try_from(config: &MaintenanceConfig) -> Result<Self, Self::Error> {
+        let timezone = match &config.timezone {
+            Some(timezone) => {
+                Tz::from_str(timezone).map_err(|_| format!("unknown time zone {:?}", timezone))?
+            }
+            None => Tz::UTC,
+        };
+        reject_foreign_fields(config)?;
+
+        match config {
+            MaintenanceConfig {
+                start: Some(start),
+                end: Some(end),
+                ..
+            } => {
+                let start = parse_timestamp(start)?;
+                let end = parse_timestamp(end)?;
+                if start >= end {
+                    return Err("maintenance has to end after its start".to_string());
+                }
+                Ok(MaintenanceWindow::OneOff { start, end })
+            }
+            MaintenanceConfig {
+                cron: Some(expression),
+                duration: Some(duration),
+                ..
+            } => {
+                if duration.is_zero() {
+                    return Err("maintenance duration has to be greater than zero".to_string());
+                }
+                Ok(MaintenanceWindow::Cron {
+                    schedule: Box::new(parse_cron(expression)?),
+                    duration: *duration,
+                    timezone,
+                })
+            }
+            MaintenanceConfig {
+                from: Some(from),
+                to: Some(to),
+                ..
+            } => {
+                let weekdays = if config.weekdays.is_empty() {
+                    WEEK.to_vec()
+                } else {
+                    config
+                        .weekdays
+                        .iter()
+                        .map(|day| {
+                            Weekday::from_str(day).map_err(|_| format!("unknown weekday {:?}", day))
+                        })
+                        .collect::<Result<_, _>>()?
+                };
+                let (from, to) = (parse_time(from)?, parse_time(to)?);
+                if from == to {
+                    return Err("maintenance has to end at another time than it starts".to_string());
+                }
+                Ok(MaintenanceWindow::Weekly {
+                    weekdays,
+                    from,
+                    to,
+                    timezone,
+                })
+            }
+            _ => Err(
+                "maintenance needs either start and end, cron and duration, or from and to"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+/// Reject the fields of another kind of window than the one selected by
+/// the given fields, e.g. a `cron` next to `start` and `end` would be ignored
+fn reject_foreign_fields(config: &MaintenanceConfig) -> Result<(), String> {
+    let (kind, fields): (&str, &[&str]) = if config.start.is_some() || config.end.is_some() {
+        ("one-off", &["start", "end"])
+    } else if config.cron.is_some() || config.duration.is_some() {
+        ("cron", &["cron", "duration", "timezone"])
+    } else {
+        ("weekly", &["weekdays", "from", "to", "timezone"])
+    };
+
+    let set = [
+        ("start", config.start.is_some()),
+        ("end", config.end.is_some()),
+        ("cron", config.cron.is_some()),
+        ("duration", config.duration.is_some()),
+        ("weekdays", !config.weekdays.is_empty()),
+        ("from", config.from.is_some()),
+        ("to", config.to.is_some()),
+        ("timezone", config.timezone.is_some()),
+    ];
+    let foreign: Vec<_> = set
+        .into_iter()
+        .filter(|(name, set)| *set && !fields.contains(name))
+        .map(|(name, _)| name)
+        .collect();
+    if !foreign.is_empty() {
+        return Err(format!(
+            "{} can't be used in a {} maintenance window",
+            foreign.join(", "),
+            kind
+        ));
+    }
+
+    Ok(())
+}
+
+const WEEK: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+fn parse_timestamp(input: &str) -> Result<DateTime<FixedOffset>, String> {
+    DateTime::parse_from_rfc3339(input)
+        .map_err(|_| format!("{:?} is not an RFC 3339 timestamp", input))
+}
+
+fn parse_time(input: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(input, "%H:%M")
+        .map_err(|_| format!("{:?} is not a HH:MM time", input))
+}
+
+/// Parse a cron expression, the usual five fields without seconds are accepted as well
+fn parse_cron(expression: &str) -> Result<Schedule, String> {
+    let expression = match expression.split_whitespace().count() {
+        5 => format!("0 {}", expression),
+        _ => expression.to_string(),
+    };
+    Schedule::from_str(&expression).map_err(|e| format!("invalid cron expression: {}", e))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn window(config: MaintenanceConfig) -> MaintenanceWindow {
+        MaintenanceWindow::try_from(&config).unwrap()
+    }
+
+    fn utc(timestamp: &str) -> DateTime<Utc> {
+        parse_timestamp(timestamp).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_one_off_window() {
+        let maintenance = window(MaintenanceConfig {
+            start: Some("2025-06-01T22:00:00+02:00".to_string()),
+            end: Some("2025-06-02T01:00:00+02:00".to_string()),
+            ..Default::default()
+        });
+
+        assert!(!maintenance.is_active(utc("2025-06-01T19:59:59Z")));
+        assert!(maintenance.is_active(utc("2025-06-01T20:00:00Z")));
+        assert!(!maintenance.is_active(utc("2025-06-01T23:00:00Z")));
+    }
+
+    #[test]
+    fn test_cron_window_in_time_zone() {
+        // Sundays 02:00 to 04:00 in Prague, UTC+2 in summer
+        let maintenance = window(MaintenanceConfig {
+            cron: Some("0 2 * * Sun".to_string()),
+            duration: Some(Duration::from_secs(7200)),
+            timezone: Some("Europe/Prague".to_string()),
+            ..Default::default()
+        });
+
+        assert!(!maintenance.is_active(utc("2025-06-01T02:30:00Z")));
+        assert!(maintenance.is_active(utc("2025-06-01T00:00:00Z")));
+        assert!(maintenance.is_active(utc("2025-06-01T01:59:00Z")));
+        assert!(!maintenance.is_active(utc("2025-06-01T02:00:00Z")));
+        assert!(!maintenance.is_active(utc("2025-06-02T00:30:00Z")));
+    }
+
+    #[test]
+    fn test_weekly_window_over_midnight() {
+        let maintenance = window(MaintenanceConfig {
+            weekdays: vec!["sat".to_string()],
+            from: Some("23:00".to_string()),
+            to: Some("01:00".to_string()),
+            ..Default::default()
+        });
+
+        // 2025-05-31 is a Saturday
+        assert!(maintenance.is_active(utc("2025-05-31T23:30:00Z")));
+        assert!(maintenance.is_active(utc("2025-06-01T00:30:00Z")));
+        assert!(!maintenance.is_active(utc("2025-06-01T23:30:00Z")));
+        assert!(!maintenance.is_active(utc("2025-05-31T00:30:00Z")));
+    }
+
+    #[test]
+    fn test_invalid_windows() {
+        let invalid = [
+            MaintenanceConfig {
+                start: Some("2025-06-02T00:00:00Z".to_string()),
+                end: Some("2025-06-01T00:00:00Z".to_string()),
+                ..Default::default()
+            },
+            MaintenanceConfig {
+                cron: Some("every sunday".to_string()),
+                duration: Some(Duration::from_secs(3600)),
+                ..Default::default()
+            },
+            MaintenanceConfig {
+                from: Some("02:00".to_string()),
+                to: Some("04:00".to_string()),
+                timezone: Some("Mars/Olympus".to_string()),
+                ..Default::default()
+            },
+            MaintenanceConfig {
+                from: Some("02:00".to_string()),
+                to: Some("02:00".to_string()),
+                ..Default::default()
+            },
+            MaintenanceConfig {
+                from: Some("02:00".to_string()),
+                to: Some("04:00".to_string()),
+                duration: Some(Duration::from_secs(3600)),
+                ..Default::default()
+            },
+            MaintenanceConfig {
+                start: Some("2025-06-01T00:00:00Z".to_string()),
+                end: Some("2025-06-02T00:00:00Z".to_string()),
+                timezone: Some("Europe/Prague".to_string()),
+                ..Default::default()
+            },
+            MaintenanceConfig {
+                start: Some("2025-06-01T00:00:00Z".to_string()),
+                ..Default::default()
+            },
+        ];
+
+        for config in invalid {
+            assert!(
+                MaintenanceWindow::try_from(&config).is_err(),
+                "{:?}",
+                config
+            );
+        }
+
+        // Fields of another kind of window are not silently ignored
+        let config = MaintenanceConfig {
+            cron: Some("0 2 * * *".to_string()),
+            duration: Some(Duration::from_secs(3600)),
+            weekdays: vec!["sun".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            MaintenanceWindow::try_from(&config).unwrap_err(),
+            "weekdays can't be used in a cron maintenance window"
+        );
+    }
+}
@@ -1,67 +1,396 @@
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
 
-use reqwest::Url;
+use chrono::{DateTime, Utc};
 use thiserror::Error;
 
-use crate::settings::ServiceConfig;
+use crate::check::{
+    Check, DiskCheck, DnsCheck, ExecCheck, FileAgeCheck, GrpcCheck, HeartbeatCheck, HttpCheck,
+    PostgresCheck, ProcessCheck, RedisCheck, TcpCheck, TlsCheck, WebSocketCheck,
+};
+use crate::maintenance::MaintenanceWindow;
+use crate::settings::{
+    Backoff, CheckType, InitialNotification, ServiceConfig, DEFAULT_CONNECT_TIMEOUT,
+    DEFAULT_FLAP_WINDOW, DEFAULT_TIMEOUT,
+};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum HealthStatus {
+    // No check has finished yet since startup
+    Unknown,
     Healthy,
+    // Passing, but responding slower than the warning latency threshold
+    Degraded,
     Unhealthy,
 }
 
+impl fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HealthStatus::Unknown => write!(f, "unknown"),
+            HealthStatus::Healthy => write!(f, "healthy"),
+            HealthStatus::Degraded => write!(f, "degraded"),
+            HealthStatus::Unhealthy => write!(f, "unhealthy"),
+        }
+    }
+}
+
+/// Reason why a single health check attempt failed
+#[derive(Debug, PartialEq, Clone, Error)]
+pub enum CheckFailure {
+    #[error("unexpected status code {0}")]
+    UnexpectedStatus(u16),
+
+    #[error("assertion failed: {0}")]
+    AssertionFailed(String),
+
+    #[error("invalid JSON response: {0}")]
+    InvalidJson(String),
+
+    #[error("request failed: {0}")]
+    Request(String),
+
+    #[error("connection failed: {0}")]
+    Connection(String),
+
+    #[error("unexpected response: {0:?}")]
+    UnexpectedResponse(String),
+
+    #[error("server error: {0}")]
+    ServerError(String),
+
+    #[error("gRPC health check failed: {0}")]
+    Grpc(String),
+
+    #[error("DNS query failed: {0}")]
+    Dns(String),
+
+    #[error("TLS error: {0}")]
+    Tls(String),
+
+    #[error("no heartbeat received within {0:?}")]
+    MissedHeartbeat(Duration),
+
+    #[error("failure reported: {0}")]
+    ReportedFailure(String),
+
+    #[error("command could not be started: {0}")]
+    CommandNotStarted(String),
+
+    #[error("command failed with {0}")]
+    CommandFailed(String),
+
+    #[error("low free space: {0}")]
+    LowFreeSpace(String),
+
+    #[error("stale file: {0}")]
+    StaleFile(String),
+
+    #[error("file not accessible: {0}")]
+    FileUnavailable(String),
+
+    #[error("process not running: {0}")]
+    ProcessNotRunning(String),
+
+    #[error("timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error("responded in {} ms, over the critical {} ms", .0.as_millis(), .1.as_millis())]
+    SlowResponse(Duration, Duration),
+
+    #[error("connection timed out after {0:?}")]
+    ConnectTimeout(Duration),
+}
+
+/// Condition of a passing check which is still worth a notification
+#[derive(Debug, PartialEq, Clone, Error)]
+pub enum CheckWarning {
+    #[error("TLS certificate expires in {days_left} days ({expires_at})")]
+    CertificateExpiring {
+        days_left: i64,
+        expires_at: DateTime<Utc>,
+        // Configured number of days the expiry date fell within
+        tier: u32,
+    },
+}
+
+impl CheckWarning {
+    /// Severity used to notify only when the warning gets more urgent
+    pub fn level(&self) -> u32 {
+        match self {
+            CheckWarning::CertificateExpiring { tier, .. } => *tier,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Error)]
 pub enum MonitoredServiceError {
     #[error("{0} is not valid url")]
     InvalidUrl(String),
+
+    #[error("invalid assertion: {0}")]
+    InvalidAssertion(String),
+
+    #[error("invalid request header: {0}")]
+    InvalidHeader(String),
+
+    #[error("missing {0}")]
+    MissingField(&'static str),
+
+    #[error("{0} is not valid address")]
+    InvalidAddress(String),
+
+    #[error("invalid CA file: {0}")]
+    InvalidCaFile(String),
+
+    #[error("invalid maintenance window: {0}")]
+    InvalidMaintenance(String),
+}
+
+/// How many times and how long to wait before a failed check is retried
+#[derive(Debug, PartialEq, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: usize,
+    pub retry_interval: Duration,
+    pub backoff: Backoff,
+    pub jitter: bool,
+    pub max_delay: Option<Duration>,
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: usize, retry_interval: Duration) -> Self {
+        Self {
+            max_retries,
+            retry_interval,
+            backoff: Backoff::Fixed,
+            jitter: false,
+            max_delay: None,
+        }
+    }
+
+    /// Delay before the retry following the given failed attempt (counted from zero)
+    pub fn delay(&self, attempt: usize) -> Duration {
+        let attempt = u32::try_from(attempt).unwrap_or(u32::MAX);
+        let delay = match self.backoff {
+            Backoff::Fixed => self.retry_interval,
+            Backoff::Linear => self
+                .retry_interval
+                .saturating_mul(attempt.saturating_add(1)),
+            Backoff::Exponential => self
+                .retry_interval
+                .saturating_mul(2u32.saturating_pow(attempt)),
+        };
+        let delay = match self.max_delay {
+            Some(max_delay) => delay.min(max_delay),
+            None => delay,
+        };
+
+        if self.jitter {
+            // Keep at least half of the delay so retries never come back to back
+            delay / 2 + delay.mul_f64(fastrand::f64() / 2.0)
+        } else {
+            delay
+        }
+    }
+}
+
+/// Detection of a service changing its status too often to be notified about every change
+#[derive(Debug, PartialEq, Clone)]
+pub struct FlapDetection {
+    // Status changes within the window which make the service flapping
+    pub threshold: usize,
+    pub window: Duration,
+    // Times of the status changes within the window
+    pub changes: VecDeque<Instant>,
+    pub flapping: bool,
+}
+
+impl FlapDetection {
+    pub fn new(threshold: usize, window: Duration) -> Self {
+        Self {
+            threshold,
+            window,
+            changes: VecDeque::new(),
+            flapping: false,
+        }
+    }
+
+    /// Record the result of a check, returns the new flapping state when the
+    /// service starts or stops flapping
+    pub fn update(&mut self, now: Instant, changed: bool) -> Option<bool> {
+        if changed {
+            self.changes.push_back(now);
+        }
+        while self
+            .changes
+            .front()
+            .is_some_and(|change| now.duration_since(*change) > self.window)
+        {
+            self.changes.pop_front();
+        }
+
+        let flapping = self.changes.len() > self.threshold;
+        if flapping == self.flapping {
+            return None;
+        }
+        self.flapping = flapping;
+        Some(flapping)
+    }
 }
 
 #[derive(Debug)]
 pub struct MonitoredService {
-    pub url: String,
-    pub interval_seconds: u64,
+    // Unique identifier, the target by default
+    pub id: String,
+    // Name shown in logs and notifications, the target by default
+    pub name: String,
+    pub group: Option<String>,
+    pub tags: Vec<String>,
+    // Address of the monitored target
+    pub target: String,
+    pub check: Check,
+    pub interval: Duration,
     pub health_status: HealthStatus,
-    pub max_retries: usize,
-    pub retry_interval: Duration,
+    pub retry_policy: RetryPolicy,
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    // Last warning notified while the service was healthy
+    pub warning: Option<CheckWarning>,
+    // Response times making the service degraded or unhealthy
+    pub warn_latency: Option<Duration>,
+    pub critical_latency: Option<Duration>,
+    // Consecutive failed and passed checks needed to change between down and up
+    pub failure_threshold: u32,
+    pub recovery_threshold: u32,
+    pub consecutive_failures: u32,
+    pub consecutive_successes: u32,
+    pub flap_detection: Option<FlapDetection>,
+    // Whether the first check result is notified
+    pub initial_notification: InitialNotification,
+    pub maintenance: Vec<MaintenanceWindow>,
+    // Status when the current maintenance window started
+    pub status_before_maintenance: Option<HealthStatus>,
+    // Ids of the services this one can't be reached without
+    pub depends_on: Vec<String>,
+    // Parent which was down when this service failed
+    pub unreachable_due_to: Option<String>,
 }
 
 impl MonitoredService {
     pub fn new(
-        url: String,
-        interval_seconds: u64,
+        check: Check,
+        interval: Duration,
         health_status: HealthStatus,
-        max_retries: usize,
-        retry_interval: Duration,
-    ) -> Result<MonitoredService, MonitoredServiceError> {
-        if !is_valid_url(&url) {
-            return Err(MonitoredServiceError::InvalidUrl(url));
-        }
-        Ok(Self {
-            url,
-            interval_seconds,
+        retry_policy: RetryPolicy,
+    ) -> MonitoredService {
+        let target = check.target();
+        Self {
+            id: target.clone(),
+            name: target.clone(),
+            group: None,
+            tags: Vec::new(),
+            target,
+            check,
+            interval,
             health_status,
-            max_retries,
-            retry_interval,
-        })
+            retry_policy,
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            warning: None,
+            warn_latency: None,
+            critical_latency: None,
+            failure_threshold: 1,
+            recovery_threshold: 1,
+            consecutive_failures: 0,
+            consecutive_successes: 0,
+            flap_detection: None,
+            initial_notification: InitialNotification::default(),
+            maintenance: Vec::new(),
+            status_before_maintenance: None,
+            depends_on: Vec::new(),
+            unreachable_due_to: None,
+        }
     }
-}
 
-fn is_valid_url(input: &str) -> bool {
-    Url::parse(input).is_ok()
+    /// Name of the service in logs and notifications, prefixed by its group
+    pub fn display_name(&self) -> String {
+        match &self.group {
+            Some(group) => format!("[{}] {}", group, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Whether any maintenance window of the service is open
+    pub fn in_maintenance(&self, now: DateTime<Utc>) -> bool {
+        self.maintenance.iter().any(|window| window.is_active(now))
+    }
 }
 
 impl TryFrom<&ServiceConfig> for MonitoredService {
     type Error = MonitoredServiceError;
 
     fn try_from(service: &ServiceConfig) -> Result<Self, Self::Error> {
-        Self::new(
-            service.url.clone(),
+        let check = match service.check_type {
+            CheckType::Http => Check::Http(HttpCheck::try_from(service)?),
+            CheckType::Tcp => Check::Tcp(TcpCheck::try_from(service)?),
+            CheckType::Dns => Check::Dns(DnsCheck::try_from(service)?),
+            CheckType::Tls => Check::Tls(TlsCheck::try_from(service)?),
+            CheckType::Heartbeat => Check::Heartbeat(HeartbeatCheck::try_from(service)?),
+            CheckType::Exec => Check::Exec(ExecCheck::try_from(service)?),
+            CheckType::Disk => Check::Disk(DiskCheck::try_from(service)?),
+            CheckType::File => Check::FileAge(FileAgeCheck::try_from(service)?),
+            CheckType::Process => Check::Process(ProcessCheck::try_from(service)?),
+            CheckType::Redis => Check::Redis(RedisCheck::try_from(service)?),
+            CheckType::Postgres => Check::Postgres(PostgresCheck::try_from(service)?),
+            CheckType::Websocket => Check::WebSocket(WebSocketCheck::try_from(service)?),
+            CheckType::Grpc => Check::Grpc(GrpcCheck::try_from(service)?),
+        };
+
+        let mut monitored_service = Self::new(
+            check,
             service.interval_seconds,
-            HealthStatus::Healthy,
-            service.max_retries,
-            Duration::from_secs(service.retry_interval),
-        )
+            HealthStatus::Unknown,
+            RetryPolicy {
+                max_retries: service.max_retries,
+                retry_interval: service.retry_interval,
+                backoff: service.backoff,
+                jitter: service.retry_jitter,
+                max_delay: service.max_retry_delay,
+            },
+        );
+        if let Some(timeout) = service.timeout {
+            monitored_service.timeout = timeout;
+        }
+        if let Some(connect_timeout) = service.connect_timeout {
+            monitored_service.connect_timeout = connect_timeout;
+        }
+        monitored_service.warn_latency = service.warn_latency_ms;
+        monitored_service.critical_latency = service.critical_latency_ms;
+        monitored_service.initial_notification = service.initial_notification.unwrap_or_default();
+        monitored_service.maintenance = service
+            .maintenance
+            .iter()
+            .map(MaintenanceWindow::try_from)
+            .collect::<Result<_, _>>()
+            .map_err(MonitoredServiceError::InvalidMaintenance)?;
+        monitored_service.id = service.id();
+        if let Some(name) = &service.name {
+            monitored_service.name = name.clone();
+        }
+        monitored_service.group = service.group.clone();
+        monitored_service.tags = service.tags.clone();
+        monitored_service.depends_on = service.depends_on.clone();
+        monitored_service.failure_threshold = service.failure_threshold.unwrap_or(1);
+        monitored_service.recovery_threshold = service.recovery_threshold.unwrap_or(1);
+        monitored_service.flap_detection = service.flap_threshold.map(|threshold| {
+            FlapDetection::new(
+                threshold as usize,
+                service.flap_window.unwrap_or(DEFAULT_FLAP_WINDOW),
+            )
+        });
+
+        Ok(monitored_service)
     }
 }
 
@@ -72,14 +401,130 @@ mod test {
     #[test]
     fn test_configuration_error_when_url_is_invalid() {
         let config = ServiceConfig {
-            url: "".to_string(),
-            interval_seconds: 3,
+            url: Some("".to_string()),
+            interval_seconds: Duration::from_secs(3),
             max_retries: 3,
-            retry_interval: 333,
+            retry_interval: Duration::from_secs(333),
+            ..Default::default()
         };
 
         let actual = MonitoredService::try_from(&config);
 
         assert!(actual.is_err());
     }
+
+    #[test]
+    fn test_tcp_service_from_config() {
+        let config = ServiceConfig {
+            check_type: CheckType::Tcp,
+            host: Some("localhost".to_string()),
+            port: Some(22),
+            expect: Some("SSH-".to_string()),
+            interval_seconds: Duration::from_secs(3),
+            max_retries: 3,
+            retry_interval: Duration::from_secs(1),
+            ..Default::default()
+        };
+
+        let service = MonitoredService::try_from(&config).unwrap();
+
+        assert_eq!(service.target, "tcp://localhost:22");
+        assert!(matches!(service.check, Check::Tcp(_)));
+
+        let config = ServiceConfig {
+            port: None,
+            ..config
+        };
+        assert_eq!(
+            MonitoredService::try_from(&config).unwrap_err(),
+            MonitoredServiceError::MissingField("host and port")
+        );
+    }
+
+    #[test]
+    fn test_service_identity_from_config() {
+        let config = ServiceConfig {
+            url: Some("http://localhost:3000/health".to_string()),
+            interval_seconds: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let service = MonitoredService::try_from(&config).unwrap();
+        assert_eq!(service.id, "http://localhost:3000/health");
+        assert_eq!(service.display_name(), "http://localhost:3000/health");
+
+        let config = ServiceConfig {
+            id: Some("api".to_string()),
+            name: Some("API".to_string()),
+            group: Some("production".to_string()),
+            tags: vec!["public".to_string()],
+            ..config
+        };
+        let service = MonitoredService::try_from(&config).unwrap();
+        assert_eq!(service.id, "api");
+        assert_eq!(service.display_name(), "[production] API");
+        assert_eq!(service.target, "http://localhost:3000/health");
+        assert_eq!(service.tags, ["public"]);
+    }
+
+    #[test]
+    fn test_flap_detection_window() {
+        let mut detection = FlapDetection::new(2, Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert_eq!(detection.update(start, true), None);
+        assert_eq!(
+            detection.update(start + Duration::from_secs(10), true),
+            None
+        );
+        assert_eq!(
+            detection.update(start + Duration::from_secs(20), false),
+            None
+        );
+        assert_eq!(
+            detection.update(start + Duration::from_secs(30), true),
+            Some(true)
+        );
+        assert_eq!(
+            detection.update(start + Duration::from_secs(40), true),
+            None
+        );
+
+        // Only the changes at 30s and 40s remain within the window
+        assert_eq!(
+            detection.update(start + Duration::from_secs(85), false),
+            Some(false)
+        );
+        assert_eq!(detection.changes.len(), 2);
+    }
+
+    #[test]
+    fn test_retry_policy_backoff() {
+        let mut policy = RetryPolicy::new(5, Duration::from_secs(2));
+        assert_eq!(policy.delay(0), Duration::from_secs(2));
+        assert_eq!(policy.delay(3), Duration::from_secs(2));
+
+        policy.backoff = Backoff::Linear;
+        assert_eq!(policy.delay(0), Duration::from_secs(2));
+        assert_eq!(policy.delay(3), Duration::from_secs(8));
+
+        policy.backoff = Backoff::Exponential;
+        assert_eq!(policy.delay(0), Duration::from_secs(2));
+        assert_eq!(policy.delay(3), Duration::from_secs(16));
+
+        policy.max_delay = Some(Duration::from_secs(10));
+        assert_eq!(policy.delay(3), Duration::from_secs(10));
+        assert_eq!(policy.delay(usize::MAX), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_retry_policy_jitter() {
+        let mut policy = RetryPolicy::new(5, Duration::from_secs(10));
+        policy.jitter = true;
+
+        for attempt in 0..100 {
+            let delay = policy.delay(attempt % 3);
+            assert!(delay >= Duration::from_secs(5));
+            assert!(delay <= Duration::from_secs(10));
+        }
+    }
 }
@@ -1,20 +1,131 @@
+use chrono::Utc;
 use log::{debug, error, info, warn};
 use reqwest::Client;
-use std::sync::Arc;
-use std::time::Duration;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
 use tokio::task::JoinHandle;
 use tokio::time;
 
+use crate::check::{Check, Heartbeats};
 use crate::error::{FluxaError, ServiceError};
-use crate::model::{HealthStatus, MonitoredService};
-use crate::notification::NotificationManager;
-use crate::settings::{ServiceConfig, ServiceConfigurationError};
+use crate::model::{CheckFailure, CheckWarning, HealthStatus, MonitoredService};
+use crate::notification::{NotificationManager, Severity};
+use crate::settings::{InitialNotification, ServiceConfig, ServiceConfigurationError};
+
+/// Longest time a new failure is held back for the services it depends on,
+/// a service checked more often waits at most its own interval
+const MAX_PARENT_WAIT: Duration = Duration::from_secs(60);
+
+/// Status of a service as last published after its check
+#[derive(Debug, Clone)]
+struct PublishedStatus {
+    status: HealthStatus,
+    published_at: Instant,
+    // Time until the service publishes its next status
+    interval: Duration,
+}
+
+/// Health status of all services by their id, shared between the monitors
+/// so a service can look up the services it depends on by their ids
+#[derive(Debug, Default, Clone)]
+pub struct ServiceStatuses {
+    statuses: Arc<RwLock<HashMap<String, PublishedStatus>>>,
+    // Names of the failing dependents by their ids, by the id of the service
+    // they depend on
+    failing_dependents: Arc<RwLock<HashMap<String, BTreeMap<String, String>>>>,
+    published: Arc<Notify>,
+}
+
+impl ServiceStatuses {
+    pub fn set(&self, id: &str, status: HealthStatus, interval: Duration) {
+        let published = PublishedStatus {
+            status,
+            published_at: Instant::now(),
+            interval,
+        };
+        self.statuses
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id.to_string(), published);
+        self.published.notify_waiters();
+    }
+
+    pub fn get(&self, id: &str) -> Option<HealthStatus> {
+        self.published(id).map(|published| published.status)
+    }
+
+    fn published(&self, id: &str) -> Option<PublishedStatus> {
+        self.statuses
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id)
+            .cloned()
+    }
+
+    /// Wait until all the given services published their status again after
+    /// `since`, at most for the longest of their intervals capped at
+    /// `max_wait`, which is also the wait when none of them published yet
+    pub async fn wait_for(&self, ids: &[String], since: Instant, max_wait: Duration) {
+        let wait = ids
+            .iter()
+            .filter_map(|id| self.published(id))
+            .map(|published| published.interval)
+            .max()
+            .map_or(max_wait, |interval| interval.min(max_wait));
+        let deadline = time::Instant::from_std(since + wait);
+
+        loop {
+            // Registered before looking at the statuses so no update is missed
+            let published = self.published.notified();
+            tokio::pin!(published);
+            published.as_mut().enable();
+
+            let all_published = ids.iter().all(|id| {
+                self.published(id)
+                    .is_some_and(|published| published.published_at >= since)
+            });
+            if all_published || time::timeout_at(deadline, published).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Record whether a dependent service is failing, so the alerts of the
+    /// services it depends on can name it
+    pub fn set_failing(&self, id: &str, name: &str, parents: &[String], failing: bool) {
+        let mut failing_dependents = self
+            .failing_dependents
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        for parent in parents {
+            let dependents = failing_dependents.entry(parent.clone()).or_default();
+            if failing {
+                dependents.insert(id.to_string(), name.to_string());
+            } else {
+                dependents.remove(id);
+            }
+        }
+    }
+
+    /// Names of the failing services depending on the given one
+    pub fn failing_dependents(&self, id: &str) -> Vec<String> {
+        self.failing_dependents
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id)
+            .map(|dependents| dependents.values().cloned().collect())
+            .unwrap_or_default()
+    }
+}
 
 #[derive(Debug)]
 pub struct ServiceMonitor {
     pub service: MonitoredService,
     pub http_client: Arc<Client>,
     pub notification_manager: Arc<NotificationManager>,
+    pub statuses: ServiceStatuses,
 }
 
 impl ServiceMonitor {
@@ -22,106 +133,471 @@ impl ServiceMonitor {
         service: MonitoredService,
         http_client: Arc<Client>,
         notification_manager: Arc<NotificationManager>,
+        statuses: ServiceStatuses,
     ) -> Self {
         Self {
             service,
             http_client,
             notification_manager,
+            statuses,
         }
     }
 
     pub async fn start_monitoring(mut self) -> Result<(), ServiceError> {
         loop {
             self.perform_health_check().await?;
-            time::sleep(Duration::from_secs(self.service.interval_seconds)).await;
+            time::sleep(self.service.interval).await;
         }
     }
 
     async fn perform_health_check(&mut self) -> Result<(), ServiceError> {
+        let check_started = Instant::now();
         let mut current_health = HealthStatus::Unhealthy;
+        let mut last_failure = None;
+        let mut warning = None;
+        let mut latency = Duration::ZERO;
+        let retry_policy = &self.service.retry_policy;
 
-        // Retry logic moved from service.rs
-        for attempt in 0..=self.service.max_retries {
-            match self.http_client.get(&self.service.url).send().await {
-                Ok(response) => {
-                    if response.status().is_success() {
-                        current_health = HealthStatus::Healthy;
-                        break;
-                    } else {
-                        debug!(
-                            "Request to {} failed with status: {}",
-                            self.service.url,
-                            response.status()
-                        );
-                    }
+        for attempt in 0..=retry_policy.max_retries {
+            let started = Instant::now();
+            let result = self
+                .service
+                .check
+                .run(
+                    &self.http_client,
+                    self.service.timeout,
+                    self.service.connect_timeout,
+                )
+                .await;
+            latency = started.elapsed();
+
+            // Responses slower than the critical threshold count as failed attempts
+            let result = match (result, self.service.critical_latency) {
+                (Ok(_), Some(critical)) if latency > critical => {
+                    Err(CheckFailure::SlowResponse(latency, critical))
                 }
-                Err(_) => {
-                    if attempt < self.service.max_retries {
+                (result, _) => result,
+            };
+
+            match result {
+                Ok(check_warning) => {
+                    current_health = match self.service.warn_latency {
+                        Some(warn) if latency > warn => HealthStatus::Degraded,
+                        _ => HealthStatus::Healthy,
+                    };
+                    last_failure = None;
+                    warning = check_warning;
+                    break;
+                }
+                Err(failure) => {
+                    if attempt < retry_policy.max_retries {
+                        let delay = retry_policy.delay(attempt);
                         debug!(
-                            "Attempt {} to {} failed. Retrying in {:?}...",
+                            "Attempt {} to {} failed: {}. Retrying in {:?}...",
                             attempt + 1,
-                            self.service.url,
-                            self.service.retry_interval
+                            self.service.display_name(),
+                            failure,
+                            delay
                         );
-                        time::sleep(self.service.retry_interval).await;
+                        time::sleep(delay).await;
                     } else {
                         debug!(
-                            "Max retries ({}) exceeded for {}",
-                            self.service.max_retries, self.service.url
+                            "Max retries ({}) exceeded for {}: {}",
+                            retry_policy.max_retries,
+                            self.service.display_name(),
+                            failure
                         );
-                        current_health = HealthStatus::Unhealthy;
-                        break;
                     }
+                    last_failure = Some(failure);
                 }
             }
         }
 
-        self.handle_status_change(current_health).await?;
+        let passing = current_health != HealthStatus::Unhealthy;
+        let current_health = self.confirmed_status(current_health);
+        self.await_parents(&current_health, check_started).await;
+        self.handle_status_change(current_health, last_failure, latency)
+            .await?;
+        self.publish_status();
+        if passing {
+            self.handle_warning(warning).await;
+        }
 
         Ok(())
     }
 
-    /// Handle health status changes and send notifications
+    /// Notify about a warning of a healthy service once per warning level
+    async fn handle_warning(&mut self, warning: Option<CheckWarning>) {
+        let level = warning.as_ref().map(CheckWarning::level);
+        if level == self.service.warning.as_ref().map(CheckWarning::level) {
+            return;
+        }
+
+        if let Some(warning) = &warning {
+            let message = format!("{} {}", self.service.display_name(), warning);
+            warn!("{}", &message);
+
+            if let Err(e) = self
+                .notification_manager
+                .send_notification(&message, Severity::Warning)
+                .await
+            {
+                error!("Problem sending notification: {:?}", e);
+            }
+        }
+        self.service.warning = warning;
+    }
+
+    /// Status of the service after a check, a failure or a recovery only
+    /// counts once it repeated for the configured number of consecutive checks
+    fn confirmed_status(&mut self, observed: HealthStatus) -> HealthStatus {
+        let service = &mut self.service;
+
+        if observed == HealthStatus::Unhealthy {
+            service.consecutive_failures += 1;
+            service.consecutive_successes = 0;
+            if service.consecutive_failures < service.failure_threshold {
+                return service.health_status.clone();
+            }
+        } else {
+            service.consecutive_successes += 1;
+            service.consecutive_failures = 0;
+            if service.health_status == HealthStatus::Unhealthy
+                && service.consecutive_successes < service.recovery_threshold
+            {
+                return service.health_status.clone();
+            }
+        }
+
+        observed
+    }
+
+    /// Handle health status changes and send notifications, a flapping
+    /// service is notified about only when it starts and stops flapping and
+    /// the initial state according to the service policy
     async fn handle_status_change(
         &mut self,
         current_health: HealthStatus,
+        failure: Option<CheckFailure>,
+        latency: Duration,
     ) -> Result<(), ServiceError> {
-        if current_health != self.service.health_status {
-            if current_health == HealthStatus::Healthy {
-                let message = format!("{} is now healthy!", self.service.url);
-                info!("{}", &message);
+        if self
+            .handle_maintenance(&current_health, &failure, latency)
+            .await
+        {
+            self.service.health_status = current_health;
+            return Ok(());
+        }
+        if self.handle_unreachable(&current_health, &failure) {
+            return Ok(());
+        }
+
+        let initial = self.service.health_status == HealthStatus::Unknown;
+        let changed = current_health != self.service.health_status;
+        // Leaving the unknown state at startup doesn't count as flapping
+        let flapping = self
+            .service
+            .flap_detection
+            .as_mut()
+            .and_then(|detection| detection.update(Instant::now(), changed && !initial));
 
-                if let Err(e) = self.notification_manager.send_notification(&message).await {
-                    error!("Problem sending notification: {:?}", e);
+        let message = match (flapping, &self.service.flap_detection) {
+            (Some(true), Some(detection)) => Some(format!(
+                "{} is flapping! {} status changes within {:?}, now {} (latency {} ms)",
+                self.service.display_name(),
+                detection.changes.len(),
+                detection.window,
+                current_health,
+                latency.as_millis()
+            )),
+            (Some(false), _) => Some(format!(
+                "{} is no longer flapping, now {} (latency {} ms)",
+                self.service.display_name(),
+                current_health,
+                latency.as_millis()
+            )),
+            (_, Some(detection)) if detection.flapping => {
+                if changed {
+                    info!(
+                        "{} is {} while flapping",
+                        self.service.display_name(),
+                        current_health
+                    );
+                }
+                None
+            }
+            _ if changed && initial => {
+                let message = self.status_message(&current_health, failure, latency);
+                match self.service.initial_notification {
+                    InitialNotification::Always => Some(message),
+                    InitialNotification::Down if current_health == HealthStatus::Unhealthy => {
+                        Some(message)
+                    }
+                    _ => {
+                        info!("{}", &message);
+                        None
+                    }
                 }
+            }
+            _ if changed => Some(self.status_message(&current_health, failure, latency)),
+            _ => None,
+        };
+
+        if let Some(message) = message {
+            if current_health == HealthStatus::Healthy && flapping.is_none() {
+                info!("{}", &message);
             } else {
-                let message = format!("{} is unhealthy!", self.service.url);
                 warn!("{}", &message);
+            }
 
-                if let Err(e) = self.notification_manager.send_notification(&message).await {
-                    error!("Problem sending notification: {:?}", e);
-                }
+            let severity = match flapping {
+                Some(true) => Severity::Warning,
+                _ => Severity::from(&current_health),
+            };
+            if let Err(e) = self
+                .notification_manager
+                .send_notification(&message, severity)
+                .await
+            {
+                error!("Problem sending notification: {:?}", e);
             }
-            self.service.health_status = current_health.clone();
+        }
+        if changed {
+            self.service.health_status = current_health;
         }
 
         Ok(())
     }
+
+    /// Record a failure while a service this one depends on is down as
+    /// unreachable due to the parent without notifying it, the status is kept
+    /// so a failure outliving the parent outage is notified as a change
+    fn handle_unreachable(
+        &mut self,
+        current_health: &HealthStatus,
+        failure: &Option<CheckFailure>,
+    ) -> bool {
+        if *current_health != HealthStatus::Unhealthy {
+            if let Some(parent) = self.service.unreachable_due_to.take() {
+                info!(
+                    "{} is reachable again after {} was down",
+                    self.service.display_name(),
+                    parent
+                );
+            }
+            return false;
+        }
+
+        let Some(parent) = self.unhealthy_parent() else {
+            self.service.unreachable_due_to = None;
+            return false;
+        };
+        if self.service.unreachable_due_to.as_ref() != Some(&parent) {
+            match failure {
+                Some(failure) => info!(
+                    "{} is unreachable due to parent {} ({})",
+                    self.service.display_name(),
+                    parent,
+                    failure
+                ),
+                None => info!(
+                    "{} is unreachable due to parent {}",
+                    self.service.display_name(),
+                    parent
+                ),
+            }
+        }
+        self.service.unreachable_due_to = Some(parent);
+        true
+    }
+
+    /// Hold back a new failure until the services this one depends on
+    /// published their status after this check started, so a failure
+    /// caused by a parent whose check finishes later is not notified
+    async fn await_parents(&self, current_health: &HealthStatus, check_started: Instant) {
+        let depends_on = &self.service.depends_on;
+        if depends_on.is_empty() {
+            return;
+        }
+
+        let failing = *current_health == HealthStatus::Unhealthy;
+        self.statuses.set_failing(
+            &self.service.id,
+            &self.service.display_name(),
+            depends_on,
+            failing,
+        );
+        if !failing
+            || self.service.health_status == HealthStatus::Unhealthy
+            || self.service.in_maintenance(Utc::now())
+            || self.unhealthy_parent().is_some()
+        {
+            return;
+        }
+
+        debug!(
+            "{} failed, waiting for the services it depends on",
+            self.service.display_name()
+        );
+        let max_wait = self.service.interval.min(MAX_PARENT_WAIT);
+        self.statuses
+            .wait_for(depends_on, check_started, max_wait)
+            .await;
+    }
+
+    /// First service this one depends on which is down or unreachable itself
+    fn unhealthy_parent(&self) -> Option<String> {
+        self.service
+            .depends_on
+            .iter()
+            .find(|parent| self.statuses.get(parent) == Some(HealthStatus::Unhealthy))
+            .cloned()
+    }
+
+    /// Share the status with the services depending on this one, an
+    /// unreachable service counts as down
+    fn publish_status(&self) {
+        let status = match self.service.unreachable_due_to {
+            Some(_) => HealthStatus::Unhealthy,
+            None => self.service.health_status.clone(),
+        };
+        self.statuses
+            .set(&self.service.id, status, self.service.interval);
+    }
+
+    /// Suppress notifications while a maintenance window is open and sum up
+    /// the window when it ends, returns whether the check result was handled
+    async fn handle_maintenance(
+        &mut self,
+        current_health: &HealthStatus,
+        failure: &Option<CheckFailure>,
+        latency: Duration,
+    ) -> bool {
+        let target = self.service.display_name();
+
+        if self.service.in_maintenance(Utc::now()) {
+            if self.service.status_before_maintenance.is_none() {
+                info!("🔧 Maintenance of {} started", target);
+                self.service.status_before_maintenance = Some(self.service.health_status.clone());
+            }
+            if *current_health != self.service.health_status {
+                info!("{} is {} during maintenance", target, current_health);
+            }
+            return true;
+        }
+
+        let Some(status_before) = self.service.status_before_maintenance.take() else {
+            return false;
+        };
+        info!("🔧 Maintenance of {} ended", target);
+
+        let details = self.status_details(current_health, failure.clone(), latency);
+        let message = match current_health {
+            HealthStatus::Unhealthy => Some(format!(
+                "{} is still unhealthy after maintenance! {}",
+                target, details
+            )),
+            _ if status_before == HealthStatus::Unhealthy => Some(format!(
+                "{} is {} after maintenance {}",
+                target, current_health, details
+            )),
+            _ => None,
+        };
+
+        if let Some(message) = message {
+            warn!("{}", &message);
+            if let Err(e) = self
+                .notification_manager
+                .send_notification(&message, Severity::from(current_health))
+                .await
+            {
+                error!("Problem sending notification: {:?}", e);
+            }
+        }
+        true
+    }
+
+    /// Failure and latency of the last check attempt shown in notifications
+    fn status_details(
+        &self,
+        current_health: &HealthStatus,
+        failure: Option<CheckFailure>,
+        latency: Duration,
+    ) -> String {
+        let latency = latency.as_millis();
+        match (current_health, failure) {
+            (HealthStatus::Unhealthy, Some(failure)) => {
+                format!("({}, latency {} ms)", failure, latency)
+            }
+            (HealthStatus::Degraded, _) => format!(
+                "(latency {} ms, over {} ms)",
+                latency,
+                self.service.warn_latency.unwrap_or_default().as_millis()
+            ),
+            _ => format!("(latency {} ms)", latency),
+        }
+    }
+
+    /// Describe the transition from the current to the new health status,
+    /// including the latency of the last check attempt and the dependent
+    /// services failing along with an unhealthy one
+    fn status_message(
+        &self,
+        current_health: &HealthStatus,
+        failure: Option<CheckFailure>,
+        latency: Duration,
+    ) -> String {
+        let target = self.service.display_name();
+        let mut details = self.status_details(current_health, failure, latency);
+        let dependents = self.statuses.failing_dependents(&self.service.id);
+        if *current_health == HealthStatus::Unhealthy && !dependents.is_empty() {
+            details = format!("{}, failing with it: {}", details, dependents.join(", "));
+        }
+
+        match (&self.service.health_status, current_health) {
+            (HealthStatus::Unknown, HealthStatus::Unhealthy) => {
+                format!("{} initial state is unhealthy! {}", target, details)
+            }
+            (HealthStatus::Unknown, _) => {
+                format!("{} initial state is {} {}", target, current_health, details)
+            }
+            (_, HealthStatus::Unhealthy) => format!("{} is unhealthy! {}", target, details),
+            (_, HealthStatus::Degraded) => format!("{} is degraded! {}", target, details),
+            (HealthStatus::Degraded, HealthStatus::Healthy) => {
+                format!("{} is no longer degraded {}", target, details)
+            }
+            (_, HealthStatus::Healthy) => format!("{} is now healthy! {}", target, details),
+            (_, HealthStatus::Unknown) => format!("{} is unknown {}", target, details),
+        }
+    }
+}
+
+/// Settings of an HTTP client, services with the same settings share a client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct HttpClientSettings {
+    connect_timeout: Duration,
+    follow_redirects: bool,
+    // Cleartext HTTP/2 without an upgrade, as gRPC talks it
+    http2_prior_knowledge: bool,
 }
 
 #[derive(Debug)]
 pub struct MonitoringService {
-    http_client: Arc<reqwest::Client>,
+    http_clients: HashMap<HttpClientSettings, Arc<reqwest::Client>>,
     notification_manager: Arc<NotificationManager>,
+    // Status of every service for the services depending on it
+    statuses: ServiceStatuses,
     service_monitors: Vec<ServiceMonitor>,
     task_handles: Vec<JoinHandle<Result<(), ServiceError>>>,
 }
 
 impl MonitoringService {
+    /// Create monitors of the configured services, heartbeat services are
+    /// registered so the web server can receive their pings
     pub fn new(
-        http_client: Arc<reqwest::Client>,
         notification_manager: Arc<NotificationManager>,
         service_configs: Vec<crate::settings::ServiceConfig>,
+        heartbeats: &Heartbeats,
     ) -> Result<Self, FluxaError> {
         debug!(
             "Creating new MonitoringService with {} services",
@@ -129,13 +605,14 @@ impl MonitoringService {
         );
 
         let mut service = Self {
-            http_client: http_client.clone(),
+            http_clients: HashMap::new(),
             notification_manager: notification_manager.clone(),
+            statuses: ServiceStatuses::default(),
             service_monitors: Vec::new(),
             task_handles: Vec::new(),
         };
 
-        service.create_services_from_config(service_configs);
+        service.create_services_from_config(service_configs, heartbeats);
 
         Ok(service)
     }
@@ -164,17 +641,17 @@ impl MonitoringService {
         let service_monitors = std::mem::take(&mut self.service_monitors);
 
         for monitor in service_monitors {
-            let service_url = monitor.service.url.clone();
-            debug!("Spawning monitoring task for: {}", service_url);
+            let service_target = monitor.service.display_name();
+            debug!("Spawning monitoring task for: {}", service_target);
 
             let handle = tokio::spawn(async move {
                 match monitor.start_monitoring().await {
                     Ok(_) => {
-                        warn!("Monitoring for {} completed unexpectedly", service_url);
+                        warn!("Monitoring for {} completed unexpectedly", service_target);
                         Ok(())
                     }
                     Err(e) => {
-                        error!("Monitoring failed for {}: {}", service_url, e);
+                        error!("Monitoring failed for {}: {}", service_target, e);
                         Err(e)
                     }
                 }
@@ -216,28 +693,640 @@ impl MonitoringService {
         }
     }
 
-    fn create_services_from_config(&mut self, service_configs: Vec<ServiceConfig>) {
+    fn create_services_from_config(
+        &mut self,
+        service_configs: Vec<ServiceConfig>,
+        heartbeats: &Heartbeats,
+    ) {
         info!(
             "Creating {} services from configuration",
             service_configs.len()
         );
 
         for config in service_configs {
-            match MonitoredService::try_from(&config) {
-                Ok(monitored_service) => {
+            let monitored_service = MonitoredService::try_from(&config)
+                .map_err(ServiceError::from)
+                .and_then(|service| {
+                    let (follow_redirects, http2_prior_knowledge) = match &service.check {
+                        Check::Http(check) => (check.follow_redirects, false),
+                        Check::Grpc(_) => (true, true),
+                        _ => (true, false),
+                    };
+                    let http_client = self.http_client_for(HttpClientSettings {
+                        connect_timeout: service.connect_timeout,
+                        follow_redirects,
+                        http2_prior_knowledge,
+                    })?;
+                    Ok((service, http_client))
+                });
+
+            match monitored_service {
+                Ok((monitored_service, http_client)) => {
+                    if let Check::Heartbeat(check) = &monitored_service.check {
+                        heartbeats.register(check.token.clone(), check.heartbeat.clone());
+                    }
                     let monitor = ServiceMonitor::new(
                         monitored_service,
-                        self.http_client.clone(),
+                        http_client,
                         self.notification_manager.clone(),
+                        self.statuses.clone(),
                     );
 
-                    debug!("Creating service monitor for: {}", monitor.service.url);
+                    debug!(
+                        "Creating service monitor for: {} ({})",
+                        monitor.service.display_name(),
+                        monitor.service.target
+                    );
                     self.service_monitors.push(monitor);
                 }
                 Err(e) => {
-                    error!("Failed to create service monitor for {}: {}", config.url, e);
+                    error!(
+                        "Failed to create service monitor for {}: {}",
+                        config.id(),
+                        e
+                    );
                 }
             }
         }
     }
+
+    /// Get a client enforcing the connection timeout, the total timeout is set per request
+    fn http_client_for(
+        &mut self,
+        settings: HttpClientSettings,
+    ) -> Result<Arc<Client>, ServiceError> {
+        if let Some(client) = self.http_clients.get(&settings) {
+            return Ok(client.clone());
+        }
+
+        let redirect = if settings.follow_redirects {
+            reqwest::redirect::Policy::default()
+        } else {
+            reqwest::redirect::Policy::none()
+        };
+        let mut builder = Client::builder()
+            .connect_timeout(settings.connect_timeout)
+            .redirect(redirect);
+        if settings.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        let client = Arc::new(builder.build()?);
+        self.http_clients.insert(settings, client.clone());
+
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::check::HttpCheck;
+    use crate::error::NotificationError;
+    use crate::maintenance::MaintenanceWindow;
+    use crate::model::{FlapDetection, RetryPolicy};
+    use crate::notification::NotificationProvider;
+    use crate::settings::{Backoff, CheckType};
+    use axum::{http::StatusCode, routing::get, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Start a stub server answering with 503 until `failures` requests were served
+    async fn start_stub_server(failures: usize) -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let app = Router::new().route(
+            "/",
+            get(move || async move {
+                if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                    StatusCode::SERVICE_UNAVAILABLE
+                } else {
+                    StatusCode::OK
+                }
+            }),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        (url, requests)
+    }
+
+    /// Provider keeping the sent notifications for assertions
+    #[derive(Default)]
+    struct RecordingProvider {
+        messages: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl NotificationProvider for RecordingProvider {
+        async fn send_notification(
+            &self,
+            message: &str,
+            _severity: Severity,
+        ) -> Result<(), NotificationError> {
+            self.messages.lock().unwrap().push(message.to_string());
+            Ok(())
+        }
+
+        fn provider_name(&self) -> &'static str {
+            "Recording"
+        }
+    }
+
+    /// Monitor of a service which was never checked, with its sent notifications
+    fn recording_monitor(
+        initial_notification: InitialNotification,
+    ) -> (ServiceMonitor, Arc<RecordingProvider>) {
+        let provider = Arc::new(RecordingProvider::default());
+        let mut notification_manager = NotificationManager::new();
+        notification_manager.add_provider(provider.clone());
+
+        let check = Check::Http(HttpCheck::new("http://localhost/".to_string()).unwrap());
+        let mut service = MonitoredService::new(
+            check,
+            Duration::from_secs(60),
+            HealthStatus::Unknown,
+            RetryPolicy::new(0, Duration::from_secs(1)),
+        );
+        service.initial_notification = initial_notification;
+        let monitor = ServiceMonitor::new(
+            service,
+            Arc::new(Client::new()),
+            Arc::new(notification_manager),
+            ServiceStatuses::default(),
+        );
+        (monitor, provider)
+    }
+
+    fn monitor(url: String, retry_policy: RetryPolicy) -> ServiceMonitor {
+        let check = Check::Http(HttpCheck::new(url).unwrap());
+        let service = MonitoredService::new(
+            check,
+            Duration::from_secs(60),
+            HealthStatus::Healthy,
+            retry_policy,
+        );
+        ServiceMonitor::new(
+            service,
+            Arc::new(Client::new()),
+            Arc::new(NotificationManager::new()),
+            ServiceStatuses::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_error_responses_are_retried_after_delay() {
+        let (url, requests) = start_stub_server(2).await;
+        let mut monitor = monitor(url, RetryPolicy::new(3, Duration::from_millis(50)));
+
+        let started = Instant::now();
+        monitor.perform_health_check().await.unwrap();
+
+        assert_eq!(monitor.service.health_status, HealthStatus::Healthy);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_expected_redirect_is_not_followed() {
+        // Redirects to itself, following it would end in a redirect loop
+        let app = Router::new().route(
+            "/",
+            get(|| async { (StatusCode::MOVED_PERMANENTLY, [("location", "/")]) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let config = ServiceConfig {
+            url: Some(url),
+            interval_seconds: Duration::from_secs(60),
+            expected_status: vec!["301".parse().unwrap()],
+            ..Default::default()
+        };
+        let mut monitoring = MonitoringService::new(
+            Arc::new(NotificationManager::new()),
+            vec![config],
+            &Heartbeats::default(),
+        )
+        .unwrap();
+        let mut monitor = monitoring.service_monitors.pop().unwrap();
+
+        monitor.perform_health_check().await.unwrap();
+
+        assert_eq!(monitor.service.health_status, HealthStatus::Healthy);
+    }
+
+    #[test]
+    fn test_http_clients_are_shared() {
+        let config = |check_type, url: &str| ServiceConfig {
+            check_type,
+            url: Some(url.to_string()),
+            interval_seconds: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let configs = vec![
+            config(CheckType::Http, "http://api/"),
+            config(CheckType::Http, "http://web/"),
+            config(CheckType::Grpc, "http://billing:50051"),
+            config(CheckType::Grpc, "http://search:50051"),
+        ];
+        let monitoring = MonitoringService::new(
+            Arc::new(NotificationManager::new()),
+            configs,
+            &Heartbeats::default(),
+        )
+        .unwrap();
+
+        assert_eq!(monitoring.service_monitors.len(), 4);
+        let mut settings: Vec<_> = monitoring.http_clients.keys().collect();
+        settings.sort_by_key(|settings| settings.http2_prior_knowledge);
+        assert_eq!(settings.len(), 2);
+        assert!(!settings[0].http2_prior_knowledge);
+        assert!(settings[1].http2_prior_knowledge);
+    }
+
+    #[tokio::test]
+    async fn test_exponential_backoff_until_retries_are_exhausted() {
+        let (url, requests) = start_stub_server(usize::MAX).await;
+        let mut retry_policy = RetryPolicy::new(3, Duration::from_millis(20));
+        retry_policy.backoff = Backoff::Exponential;
+        let mut monitor = monitor(url, retry_policy);
+
+        let started = Instant::now();
+        monitor.perform_health_check().await.unwrap();
+
+        assert_eq!(monitor.service.health_status, HealthStatus::Unhealthy);
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+        // 20ms + 40ms + 80ms between the four attempts
+        assert!(started.elapsed() >= Duration::from_millis(140));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_is_reported_as_failure_reason() {
+        let app = Router::new().route(
+            "/",
+            get(|| async {
+                time::sleep(Duration::from_secs(5)).await;
+                "OK"
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let check = Check::Http(HttpCheck::new(url).unwrap());
+        let timeout = Duration::from_millis(100);
+
+        assert_eq!(
+            check.run(&Client::new(), timeout, timeout).await,
+            Err(CheckFailure::Timeout(timeout))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_slow_responses_degrade_the_service() {
+        let app = Router::new().route(
+            "/",
+            get(|| async {
+                time::sleep(Duration::from_millis(150)).await;
+                "OK"
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut monitor = monitor(url, RetryPolicy::new(0, Duration::from_millis(10)));
+        monitor.service.warn_latency = Some(Duration::from_millis(50));
+        monitor.perform_health_check().await.unwrap();
+        assert_eq!(monitor.service.health_status, HealthStatus::Degraded);
+
+        monitor.service.critical_latency = Some(Duration::from_millis(100));
+        monitor.perform_health_check().await.unwrap();
+        assert_eq!(monitor.service.health_status, HealthStatus::Unhealthy);
+
+        monitor.service.warn_latency = Some(Duration::from_secs(4));
+        monitor.service.critical_latency = Some(Duration::from_secs(5));
+        monitor.perform_health_check().await.unwrap();
+        assert_eq!(monitor.service.health_status, HealthStatus::Healthy);
+    }
+
+    #[test]
+    fn test_status_messages_include_latency() {
+        let mut monitor = monitor(
+            "http://localhost/".to_string(),
+            RetryPolicy::new(0, Duration::from_secs(1)),
+        );
+        monitor.service.warn_latency = Some(Duration::from_millis(500));
+        let latency = Duration::from_millis(730);
+
+        assert_eq!(
+            monitor.status_message(&HealthStatus::Degraded, None, latency),
+            "http://localhost/ is degraded! (latency 730 ms, over 500 ms)"
+        );
+        assert_eq!(
+            monitor.status_message(
+                &HealthStatus::Unhealthy,
+                Some(CheckFailure::UnexpectedStatus(502)),
+                latency
+            ),
+            "http://localhost/ is unhealthy! (unexpected status code 502, latency 730 ms)"
+        );
+
+        monitor.service.health_status = HealthStatus::Degraded;
+        assert_eq!(
+            monitor.status_message(&HealthStatus::Healthy, None, latency),
+            "http://localhost/ is no longer degraded (latency 730 ms)"
+        );
+
+        monitor.service.health_status = HealthStatus::Unhealthy;
+        assert_eq!(
+            monitor.status_message(&HealthStatus::Healthy, None, latency),
+            "http://localhost/ is now healthy! (latency 730 ms)"
+        );
+    }
+
+    #[test]
+    fn test_failure_and_recovery_thresholds() {
+        let mut monitor = monitor(
+            "http://localhost/".to_string(),
+            RetryPolicy::new(0, Duration::from_secs(1)),
+        );
+        monitor.service.failure_threshold = 2;
+        monitor.service.recovery_threshold = 3;
+        let mut check = |observed| {
+            let status = monitor.confirmed_status(observed);
+            monitor.service.health_status = status.clone();
+            status
+        };
+
+        assert_eq!(check(HealthStatus::Unhealthy), HealthStatus::Healthy);
+        assert_eq!(check(HealthStatus::Healthy), HealthStatus::Healthy);
+        assert_eq!(check(HealthStatus::Unhealthy), HealthStatus::Healthy);
+        assert_eq!(check(HealthStatus::Unhealthy), HealthStatus::Unhealthy);
+
+        assert_eq!(check(HealthStatus::Healthy), HealthStatus::Unhealthy);
+        assert_eq!(check(HealthStatus::Degraded), HealthStatus::Unhealthy);
+        assert_eq!(check(HealthStatus::Healthy), HealthStatus::Healthy);
+    }
+
+    #[tokio::test]
+    async fn test_flapping_service_changes_status_silently() {
+        let (mut monitor, provider) = recording_monitor(InitialNotification::Always);
+        monitor.service.health_status = HealthStatus::Healthy;
+        monitor.service.flap_detection = Some(FlapDetection::new(2, Duration::from_millis(200)));
+        let latency = Duration::from_millis(5);
+
+        for status in [HealthStatus::Unhealthy, HealthStatus::Healthy] {
+            monitor
+                .handle_status_change(status, None, latency)
+                .await
+                .unwrap();
+        }
+        assert!(!monitor.service.flap_detection.as_ref().unwrap().flapping);
+
+        monitor
+            .handle_status_change(HealthStatus::Unhealthy, None, latency)
+            .await
+            .unwrap();
+        assert!(monitor.service.flap_detection.as_ref().unwrap().flapping);
+        assert_eq!(monitor.service.health_status, HealthStatus::Unhealthy);
+        assert_eq!(
+            provider.messages.lock().unwrap().last().unwrap(),
+            "http://localhost/ is flapping! 3 status changes within 200ms, now unhealthy (latency 5 ms)"
+        );
+
+        // The status changes leave the window while the service stays down
+        time::sleep(Duration::from_millis(250)).await;
+        monitor
+            .handle_status_change(HealthStatus::Unhealthy, None, latency)
+            .await
+            .unwrap();
+        assert!(!monitor.service.flap_detection.as_ref().unwrap().flapping);
+        assert_eq!(
+            provider.messages.lock().unwrap().last().unwrap(),
+            "http://localhost/ is no longer flapping, now unhealthy (latency 5 ms)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_initial_state_notification_policy() {
+        let latency = Duration::from_millis(12);
+
+        let (mut monitor, provider) = recording_monitor(InitialNotification::Always);
+        for status in [HealthStatus::Healthy, HealthStatus::Unhealthy] {
+            monitor
+                .handle_status_change(status, None, latency)
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            *provider.messages.lock().unwrap(),
+            [
+                "http://localhost/ initial state is healthy (latency 12 ms)",
+                "http://localhost/ is unhealthy! (latency 12 ms)"
+            ]
+        );
+
+        let (mut monitor, provider) = recording_monitor(InitialNotification::Down);
+        monitor
+            .handle_status_change(HealthStatus::Healthy, None, latency)
+            .await
+            .unwrap();
+        assert!(provider.messages.lock().unwrap().is_empty());
+
+        let (mut monitor, provider) = recording_monitor(InitialNotification::Down);
+        let failure = Some(CheckFailure::UnexpectedStatus(503));
+        monitor
+            .handle_status_change(HealthStatus::Unhealthy, failure, latency)
+            .await
+            .unwrap();
+        assert_eq!(
+            *provider.messages.lock().unwrap(),
+            ["http://localhost/ initial state is unhealthy! (unexpected status code 503, latency 12 ms)"]
+        );
+
+        let (mut monitor, provider) = recording_monitor(InitialNotification::Silent);
+        monitor
+            .handle_status_change(HealthStatus::Unhealthy, None, latency)
+            .await
+            .unwrap();
+        assert!(provider.messages.lock().unwrap().is_empty());
+        assert_eq!(monitor.service.health_status, HealthStatus::Unhealthy);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_suppresses_notifications_until_summary() {
+        let (mut monitor, provider) = recording_monitor(InitialNotification::Always);
+        monitor.service.health_status = HealthStatus::Healthy;
+        let now = Utc::now();
+        let window = |start: chrono::DateTime<Utc>| MaintenanceWindow::OneOff {
+            start: start.fixed_offset(),
+            end: (start + chrono::TimeDelta::hours(1)).fixed_offset(),
+        };
+        let latency = Duration::from_millis(3);
+
+        monitor.service.maintenance = vec![window(now - chrono::TimeDelta::minutes(5))];
+        for status in [
+            HealthStatus::Unhealthy,
+            HealthStatus::Healthy,
+            HealthStatus::Unhealthy,
+        ] {
+            monitor
+                .handle_status_change(status, None, latency)
+                .await
+                .unwrap();
+        }
+        assert!(provider.messages.lock().unwrap().is_empty());
+        assert_eq!(monitor.service.health_status, HealthStatus::Unhealthy);
+
+        // The window is over by the next check
+        monitor.service.maintenance = vec![window(now - chrono::TimeDelta::hours(2))];
+        let failure = Some(CheckFailure::Connection("refused".to_string()));
+        for _ in 0..2 {
+            monitor
+                .handle_status_change(HealthStatus::Unhealthy, failure.clone(), latency)
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            *provider.messages.lock().unwrap(),
+            ["http://localhost/ is still unhealthy after maintenance! (connection failed: refused, latency 3 ms)"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failures_during_parent_outage_are_not_notified() {
+        let (mut monitor, provider) = recording_monitor(InitialNotification::Always);
+        monitor.service.health_status = HealthStatus::Healthy;
+        monitor.service.depends_on = vec!["http://gateway/".to_string()];
+        let statuses = monitor.statuses.clone();
+        let latency = Duration::from_millis(3);
+
+        statuses.set(
+            "http://gateway/",
+            HealthStatus::Unhealthy,
+            Duration::from_secs(60),
+        );
+        for status in [
+            HealthStatus::Unhealthy,
+            HealthStatus::Healthy,
+            HealthStatus::Unhealthy,
+        ] {
+            monitor
+                .handle_status_change(status, None, latency)
+                .await
+                .unwrap();
+            monitor.publish_status();
+        }
+        assert!(provider.messages.lock().unwrap().is_empty());
+        assert_eq!(
+            monitor.service.unreachable_due_to.as_deref(),
+            Some("http://gateway/")
+        );
+        // Services depending on this one see it down
+        assert_eq!(
+            statuses.get("http://localhost/"),
+            Some(HealthStatus::Unhealthy)
+        );
+
+        // The failure outlives the parent outage
+        statuses.set(
+            "http://gateway/",
+            HealthStatus::Healthy,
+            Duration::from_secs(60),
+        );
+        monitor
+            .handle_status_change(HealthStatus::Unhealthy, None, latency)
+            .await
+            .unwrap();
+        assert_eq!(
+            *provider.messages.lock().unwrap(),
+            ["http://localhost/ is unhealthy! (latency 3 ms)"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_parents_is_capped() {
+        let statuses = ServiceStatuses::default();
+        let parents = ["gateway".to_string()];
+        statuses.set("gateway", HealthStatus::Healthy, Duration::from_secs(3600));
+
+        let started = Instant::now();
+        statuses
+            .wait_for(&parents, started, Duration::from_millis(100))
+            .await;
+        let waited = started.elapsed();
+        assert!(waited >= Duration::from_millis(100), "{:?}", waited);
+        assert!(waited < Duration::from_secs(1), "{:?}", waited);
+
+        // A status published after the check started ends the wait
+        let started = Instant::now();
+        statuses.set("gateway", HealthStatus::Healthy, Duration::from_secs(3600));
+        statuses
+            .wait_for(&parents, started, Duration::from_secs(60))
+            .await;
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_failing_dependents_with_the_same_name() {
+        let statuses = ServiceStatuses::default();
+        let parents = ["gateway".to_string()];
+
+        statuses.set_failing("api-eu", "http://api/", &parents, true);
+        statuses.set_failing("api-us", "http://api/", &parents, true);
+        assert_eq!(
+            statuses.failing_dependents("gateway"),
+            ["http://api/", "http://api/"]
+        );
+
+        statuses.set_failing("api-eu", "http://api/", &parents, false);
+        assert_eq!(statuses.failing_dependents("gateway"), ["http://api/"]);
+        assert!(statuses.failing_dependents("api-us").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_child_failing_before_its_parent_is_not_notified() {
+        let provider = Arc::new(RecordingProvider::default());
+        let mut notification_manager = NotificationManager::new();
+        notification_manager.add_provider(provider.clone());
+        let notification_manager = Arc::new(notification_manager);
+        let statuses = ServiceStatuses::default();
+
+        let (parent_url, _) = start_stub_server(usize::MAX).await;
+        let (child_url, _) = start_stub_server(usize::MAX).await;
+        let mut parent = monitor(parent_url, RetryPolicy::new(0, Duration::from_secs(1)));
+        let mut child = monitor(child_url, RetryPolicy::new(0, Duration::from_secs(1)));
+        child.service.depends_on = vec![parent.service.id.clone()];
+        for monitor in [&mut parent, &mut child] {
+            monitor.notification_manager = notification_manager.clone();
+            monitor.statuses = statuses.clone();
+            monitor.publish_status();
+        }
+
+        // The child check finishes first and waits for the parent's next status
+        let child = tokio::spawn(async move {
+            child.perform_health_check().await.unwrap();
+            child
+        });
+        time::sleep(Duration::from_millis(200)).await;
+        assert!(!child.is_finished());
+        parent.perform_health_check().await.unwrap();
+        let child = child.await.unwrap();
+
+        assert_eq!(
+            child.service.unreachable_due_to,
+            Some(parent.service.id.clone())
+        );
+        let messages = provider.messages.lock().unwrap();
+        assert_eq!(messages.len(), 1, "{:?}", messages);
+        assert!(messages[0].starts_with(&format!("{} is unhealthy!", parent.service.id)));
+        assert!(
+            messages[0].ends_with(&format!(", failing with it: {}", child.service.id)),
+            "{}",
+            messages[0]
+        );
+    }
 }
@@ -4,8 +4,27 @@ use serde_json;
 use std::sync::Arc;
 
 use crate::error::NotificationError;
+use crate::model::HealthStatus;
 use crate::settings::FluxaConfig;
 
+/// How serious a notification is, providers may format it accordingly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Alert,
+    Warning,
+    Recovery,
+}
+
+impl From<&HealthStatus> for Severity {
+    fn from(status: &HealthStatus) -> Self {
+        match status {
+            HealthStatus::Unhealthy => Severity::Alert,
+            HealthStatus::Degraded => Severity::Warning,
+            HealthStatus::Healthy | HealthStatus::Unknown => Severity::Recovery,
+        }
+    }
+}
+
 pub struct NotificationManager {
     providers: Vec<Arc<dyn NotificationProvider>>,
 }
@@ -74,7 +93,11 @@ impl NotificationManager {
     }
 
     /// Send notification to all configured providers
-    pub async fn send_notification(&self, message: &str) -> Result<(), NotificationError> {
+    pub async fn send_notification(
+        &self,
+        message: &str,
+        severity: Severity,
+    ) -> Result<(), NotificationError> {
         if self.providers.is_empty() {
             debug!("No notification providers configured, skipping notification");
             return Ok(());
@@ -89,7 +112,7 @@ impl NotificationManager {
         let mut errors = Vec::new();
 
         for provider in &self.providers {
-            match provider.send_notification(message).await {
+            match provider.send_notification(message, severity).await {
                 Ok(_) => {
                     debug!("✅ Notification sent via {}", provider.provider_name());
                 }
@@ -109,7 +132,7 @@ impl NotificationManager {
         Ok(())
     }
 
-    fn add_provider(&mut self, provider: Arc<dyn NotificationProvider>) {
+    pub(crate) fn add_provider(&mut self, provider: Arc<dyn NotificationProvider>) {
         debug!("Adding notification provider: {}", provider.provider_name());
         self.providers.push(provider);
     }
@@ -117,7 +140,11 @@ impl NotificationManager {
 
 #[async_trait::async_trait]
 pub trait NotificationProvider: Send + Sync {
-    async fn send_notification(&self, message: &str) -> Result<(), NotificationError>;
+    async fn send_notification(
+        &self,
+        message: &str,
+        severity: Severity,
+    ) -> Result<(), NotificationError>;
     fn provider_name(&self) -> &'static str;
 }
 
@@ -140,7 +167,11 @@ impl PushoverProvider {
 
 #[async_trait::async_trait]
 impl NotificationProvider for PushoverProvider {
-    async fn send_notification(&self, message: &str) -> Result<(), NotificationError> {
+    async fn send_notification(
+        &self,
+        message: &str,
+        _severity: Severity,
+    ) -> Result<(), NotificationError> {
         let params = serde_json::json!({
             "token": self.api_key,
             "user": self.user_key,
@@ -191,7 +222,11 @@ impl ConsoleProvider {
 
 #[async_trait::async_trait]
 impl NotificationProvider for ConsoleProvider {
-    async fn send_notification(&self, message: &str) -> Result<(), NotificationError> {
+    async fn send_notification(
+        &self,
+        message: &str,
+        _severity: Severity,
+    ) -> Result<(), NotificationError> {
         println!("🔔 [CONSOLE NOTIFICATION]: {}", message);
         Ok(())
     }
@@ -217,12 +252,14 @@ impl TelegramProvider {
         }
     }
 
-    fn format_message(&self, message: &str) -> String {
-        // Rich HTML formatting for Telegram
+    fn format_message(&self, message: &str, severity: Severity) -> String {
+        // Rich HTML formatting for Telegram, the message may contain excerpts
+        // of command output or response bodies so it is escaped
         let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC");
+        let message = escape_html(message);
 
-        if message.contains("unhealthy") || message.contains("down") {
-            format!(
+        match severity {
+            Severity::Alert => format!(
                 "🚨 <b>Service Alert</b>\n\n\
                  📋 <b>Message:</b> {}\n\
                  ⏰ <b>Time:</b> {}\n\
@@ -230,9 +267,17 @@ impl TelegramProvider {
                 message,
                 timestamp,
                 env!("CARGO_PKG_VERSION")
-            )
-        } else {
-            format!(
+            ),
+            Severity::Warning => format!(
+                "⚠️ <b>Service Warning</b>\n\n\
+                 📋 <b>Message:</b> {}\n\
+                 ⏰ <b>Time:</b> {}\n\
+                 🔧 <i>Fluxa Monitor v{}</i>",
+                message,
+                timestamp,
+                env!("CARGO_PKG_VERSION")
+            ),
+            Severity::Recovery => format!(
                 "✅ <b>Service Recovery</b>\n\n\
                  📋 <b>Message:</b> {}\n\
                  ⏰ <b>Time:</b> {}\n\
@@ -240,17 +285,28 @@ impl TelegramProvider {
                 message,
                 timestamp,
                 env!("CARGO_PKG_VERSION")
-            )
+            ),
         }
     }
 }
 
+/// Escape the characters Telegram treats as HTML markup
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
 #[async_trait::async_trait]
 impl NotificationProvider for TelegramProvider {
-    async fn send_notification(&self, message: &str) -> Result<(), NotificationError> {
+    async fn send_notification(
+        &self,
+        message: &str,
+        severity: Severity,
+    ) -> Result<(), NotificationError> {
         let url = format!("https://api.telegram.org/bot{}/sendMessage", self.bot_token);
 
-        let formatted_message = self.format_message(message);
+        let formatted_message = self.format_message(message, severity);
 
         let payload = serde_json::json!({
             "chat_id": self.chat_id,
@@ -288,3 +344,38 @@ impl NotificationProvider for TelegramProvider {
         "Telegram"
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn telegram_provider() -> TelegramProvider {
+        TelegramProvider::new(
+            "token".to_string(),
+            "42".to_string(),
+            Arc::new(reqwest::Client::new()),
+        )
+    }
+
+    #[test]
+    fn test_telegram_message_is_escaped() {
+        let message = telegram_provider().format_message(
+            "backup is unhealthy! (exit status <1>: tar & gzip failed)",
+            Severity::Alert,
+        );
+
+        assert!(message.starts_with("🚨 <b>Service Alert</b>"));
+        assert!(message.contains("exit status &lt;1&gt;: tar &amp; gzip failed"));
+    }
+
+    #[test]
+    fn test_telegram_header_follows_severity() {
+        let provider = telegram_provider();
+
+        // The wording of the message doesn't matter
+        let message = provider.format_message("shop is down for good", Severity::Recovery);
+        assert!(message.starts_with("✅ <b>Service Recovery</b>"));
+        let message = provider.format_message("shop is slow", Severity::Warning);
+        assert!(message.starts_with("⚠️ <b>Service Warning</b>"));
+    }
+}
@@ -0,0 +1,109 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Replace `${NAME}` with the value of the environment variable `NAME`,
+/// `$${` is kept as a literal `${`. Errors never contain the value itself
+pub fn interpolate(value: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            result.push_str(&rest[..start - 1]);
+            result.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        result.push_str(&rest[..start]);
+
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| "unclosed \"${\" in the value".to_string())?;
+        let name = &rest[start + 2..start + end];
+        if name.is_empty() {
+            return Err("empty \"${}\" in the value".to_string());
+        }
+        let variable = env::var(name).map_err(|e| match e {
+            env::VarError::NotPresent => format!("environment variable {} is not set", name),
+            env::VarError::NotUnicode(_) => {
+                format!("environment variable {} is not valid unicode", name)
+            }
+        })?;
+        result.push_str(&variable);
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+/// Read a secret from a file, e.g. a Docker or systemd secret, without the
+/// trailing newline
+pub fn read_file(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path)
+        .map(|content| content.trim_end_matches(['\r', '\n']).to_string())
+        .map_err(|e| format!("failed to read {:?}: {}", path, e))
+}
+
+/// Resolve a secret given either inline, where `${NAME}` is interpolated,
+/// or by its `<name>_file` variant
+pub fn resolve(name: &str, value: &mut String, file: Option<&Path>) -> Result<(), String> {
+    let resolved = match file {
+        Some(_) if !value.is_empty() => {
+            return Err(format!("set either {} or {}_file, not both", name, name))
+        }
+        Some(file) => read_file(file),
+        None => interpolate(value),
+    };
+    *value = resolved.map_err(|e| format!("{}: {}", name, e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_interpolate() {
+        env::set_var("FLUXA_TEST_TOKEN", "s3cret");
+
+        assert_eq!(
+            interpolate("Bearer ${FLUXA_TEST_TOKEN}"),
+            Ok("Bearer s3cret".to_string())
+        );
+        assert_eq!(
+            interpolate("${FLUXA_TEST_TOKEN}:${FLUXA_TEST_TOKEN}"),
+            Ok("s3cret:s3cret".to_string())
+        );
+        assert_eq!(interpolate("pa$$word"), Ok("pa$$word".to_string()));
+        assert_eq!(
+            interpolate("$${FLUXA_TEST_TOKEN}"),
+            Ok("${FLUXA_TEST_TOKEN}".to_string())
+        );
+        assert_eq!(
+            interpolate("${FLUXA_TEST_MISSING}"),
+            Err("environment variable FLUXA_TEST_MISSING is not set".to_string())
+        );
+        assert!(interpolate("${FLUXA_TEST_TOKEN").is_err());
+        assert!(interpolate("${}").is_err());
+    }
+
+    #[test]
+    fn test_resolve_from_file() {
+        let path = env::temp_dir().join(format!("fluxa-{}-secret", std::process::id()));
+        fs::write(&path, "from-file\n").unwrap();
+
+        let mut value = String::new();
+        resolve("bot_token", &mut value, Some(&path)).unwrap();
+        assert_eq!(value, "from-file");
+
+        let mut value = "inline".to_string();
+        assert!(resolve("bot_token", &mut value, Some(&path)).is_err());
+
+        fs::remove_file(&path).unwrap();
+        let error = resolve("bot_token", &mut String::new(), Some(&path)).unwrap_err();
+        assert!(error.starts_with("bot_token: failed to read"), "{}", error);
+    }
+}
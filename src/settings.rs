@@ -1,25 +1,707 @@
-use std::{path::Path, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
-use config::{Config, ConfigError, File, FileFormat};
+use config::{Config, ConfigError, File, FileFormat, Map, Source, Value, ValueKind};
+use log::warn;
 use serde::Deserialize;
 use thiserror::Error;
 
+use crate::assertion::JsonAssertion;
+use crate::check::{heartbeat_id, heartbeat_target, parse_nameserver};
+use crate::duration;
+use crate::maintenance::MaintenanceWindow;
+use crate::secret;
+
 #[derive(Debug, Default, Deserialize, PartialEq, Eq, Clone)]
 pub struct ServiceConfig {
-    pub url: String,
-    pub interval_seconds: u64,
+    // Unique identifier referenced by depends_on (optional) - the monitored target by default
+    pub id: Option<String>,
+    // Name shown in logs and notifications (optional) - the monitored target by default
+    pub name: Option<String>,
+    // Group the service belongs to (optional) - e.g. production
+    pub group: Option<String>,
+    // Free-form labels of the service (optional)
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // Kind of the check (optional) - HTTP by default
+    #[serde(default, rename = "type")]
+    pub check_type: CheckType,
+    // Monitored url - required by HTTP, WebSocket and gRPC checks
+    pub url: Option<String>,
+    // Monitored host and port - required by TCP checks, TLS checks use port 443 by default
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    // Payload sent after a TCP connection or WebSocket handshake is established (optional)
+    pub send: Option<String>,
+    // Text a TCP or WebSocket peer has to respond with (optional) - e.g. a banner
+    pub expect: Option<String>,
+    // Queried nameserver address - required by DNS checks, port 53 by default
+    pub nameserver: Option<String>,
+    // Queried domain name - required by DNS checks
+    pub domain: Option<String>,
+    // Queried record type (optional) - A by default
+    #[serde(default)]
+    pub record_type: DnsRecordType,
+    // Transport of the DNS query (optional) - UDP by default
+    #[serde(default)]
+    pub dns_protocol: DnsProtocol,
+    // Values which have to be present among the DNS answers (optional)
+    #[serde(default)]
+    pub expected_records: Vec<String>,
+    // Name the TLS certificate has to be valid for (optional) - host by default
+    pub server_name: Option<String>,
+    // Days before certificate expiry when warnings are sent (optional) - 21, 7 and 1 by default
+    pub expiry_warning_days: Option<Vec<u32>>,
+    // PEM file with additional trusted CA certificates (optional)
+    pub ca_file: Option<PathBuf>,
+    // Secret part of the `/push/{token}` URL - required by heartbeat checks
+    pub token: Option<String>,
+    // File the token is read from instead (optional)
+    pub token_file: Option<PathBuf>,
+    // Expected time between two heartbeat pings - required by heartbeat checks
+    #[serde(default, deserialize_with = "duration::optional_seconds")]
+    pub period: Option<Duration>,
+    // Time a late heartbeat ping is tolerated (optional) - none by default
+    #[serde(default, deserialize_with = "duration::optional_seconds")]
+    pub grace: Option<Duration>,
+    // Executed program - required by exec checks
+    pub command: Option<String>,
+    // Arguments of the executed program (optional)
+    #[serde(default)]
+    pub args: Vec<String>,
+    // Environment variables added for the executed program (optional)
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    // Checked mount point or file - required by disk and file checks
+    pub path: Option<PathBuf>,
+    // Minimal percentage of free space and inodes on the mount (optional)
+    pub min_free_percent: Option<u8>,
+    pub min_free_inodes_percent: Option<u8>,
+    // Maximal age of the file since its last modification - required by file checks
+    #[serde(default, deserialize_with = "duration::optional_seconds")]
+    pub max_age: Option<Duration>,
+    // Name or PID file of the process - one of them is required by process checks
+    pub process_name: Option<String>,
+    pub pid_file: Option<PathBuf>,
+    // Credentials of datastore checks (optional) - Redis AUTH or the PostgreSQL user
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // File the password is read from instead (optional) - e.g. a Docker secret
+    pub password_file: Option<PathBuf>,
+    // Database named in the PostgreSQL startup message (optional)
+    pub database: Option<String>,
+    // Service asked for by gRPC health checks (optional) - the whole server by default
+    pub grpc_service: Option<String>,
+    // Time between two checks - integer seconds or a duration like "5m"
+    #[serde(deserialize_with = "duration::seconds")]
+    pub interval_seconds: Duration,
     pub max_retries: usize,
-    pub retry_interval: u64,
+    // Time before the next attempt of a failed check
+    #[serde(deserialize_with = "duration::seconds")]
+    pub retry_interval: Duration,
+    // Accepted status codes (optional) - any 2xx code when empty
+    #[serde(default)]
+    pub expected_status: Vec<StatusCodeRange>,
+    // Response assertions (optional) - all of them have to pass
+    #[serde(default)]
+    pub assertions: Vec<AssertionConfig>,
+    // JSON body assertions (optional) - e.g. `$.status == "ok"`
+    #[serde(default)]
+    pub json_assertions: Vec<String>,
+    // HTTP request method (optional) - GET by default
+    #[serde(default)]
+    pub method: HttpMethod,
+    // Additional request headers (optional) - e.g. Authorization or User-Agent
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    // Request headers whose values are read from files (optional)
+    #[serde(default)]
+    pub header_files: BTreeMap<String, PathBuf>,
+    // Follow redirects of the checked url (optional) - off when expected_status lists a 3xx code
+    pub follow_redirects: Option<bool>,
+    // Raw request body (optional)
+    pub body: Option<String>,
+    // JSON request body (optional) - sent with `Content-Type: application/json`
+    pub json_body: Option<serde_json::Value>,
+    // Total request timeout (optional) - `[fluxa]` timeout by default
+    #[serde(default, deserialize_with = "duration::optional_seconds")]
+    pub timeout: Option<Duration>,
+    // Connection timeout (optional) - `[fluxa]` connect_timeout by default
+    #[serde(default, deserialize_with = "duration::optional_seconds")]
+    pub connect_timeout: Option<Duration>,
+    // How the delay between retries grows (optional) - fixed by default
+    #[serde(default)]
+    pub backoff: Backoff,
+    // Randomize retry delays so checks don't retry in lockstep (optional)
+    #[serde(default)]
+    pub retry_jitter: bool,
+    // Upper bound of the retry delay (optional)
+    #[serde(default, deserialize_with = "duration::optional_seconds")]
+    pub max_retry_delay: Option<Duration>,
+    // Response time above which the service is degraded (optional) - integers are milliseconds
+    #[serde(default, deserialize_with = "duration::optional_milliseconds")]
+    pub warn_latency_ms: Option<Duration>,
+    // Response time above which the service is unhealthy (optional) - integers are milliseconds
+    #[serde(default, deserialize_with = "duration::optional_milliseconds")]
+    pub critical_latency_ms: Option<Duration>,
+    // Consecutive failed checks before the service is down (optional) - 1 by default
+    pub failure_threshold: Option<u32>,
+    // Consecutive passed checks before a down service is up again (optional) - 1 by default
+    pub recovery_threshold: Option<u32>,
+    // Status changes within the flap window which make the service flapping (optional)
+    pub flap_threshold: Option<u32>,
+    // Window of the flap detection (optional) - an hour by default
+    #[serde(default, deserialize_with = "duration::optional_seconds")]
+    pub flap_window: Option<Duration>,
+    // Notification about the first check result (optional) - `[fluxa]` initial_notification by default
+    pub initial_notification: Option<InitialNotification>,
+    // Windows when status changes are not notified (optional) - global windows are added
+    #[serde(default)]
+    pub maintenance: Vec<MaintenanceConfig>,
+    // Ids of services this one can't be reached without (optional) - e.g. a gateway
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    // File the service is defined in, named in configuration errors
+    #[serde(skip)]
+    pub origin: Option<String>,
 }
 
+/// Maintenance window, either one-off with `start` and `end`, recurring by
+/// `cron` for `duration` seconds or recurring on `weekdays` `from` and `to`
 #[derive(Debug, Default, Deserialize, PartialEq, Eq, Clone)]
+pub struct MaintenanceConfig {
+    // RFC 3339 timestamps of a one-off window
+    pub start: Option<String>,
+    pub end: Option<String>,
+    // Cron expression of the window starts and the window length
+    pub cron: Option<String>,
+    #[serde(default, deserialize_with = "duration::optional_seconds")]
+    pub duration: Option<Duration>,
+    // Weekdays of the window (optional) - every day by default
+    #[serde(default)]
+    pub weekdays: Vec<String>,
+    // Local times `HH:MM` of the window start and end
+    pub from: Option<String>,
+    pub to: Option<String>,
+    // Time zone of recurring windows (optional) - UTC by default
+    pub timezone: Option<String>,
+}
+
+/// Whether the first check result after startup is notified
+#[derive(Debug, Default, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum InitialNotification {
+    /// Notify the initial state whatever it is
+    Always,
+    /// Notify the initial state only when the service is down
+    #[default]
+    Down,
+    /// Only log the initial state
+    Silent,
+}
+
+/// Strategy for growing the delay between retries
+#[derive(Debug, Default, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Backoff {
+    /// Always wait `retry_interval`
+    #[default]
+    Fixed,
+    /// Wait `retry_interval` multiplied by the attempt number
+    Linear,
+    /// Double the delay after every attempt
+    Exponential,
+}
+
+/// Kind of the service health check
+#[derive(Debug, Default, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckType {
+    /// HTTP request expecting a healthy response
+    #[default]
+    Http,
+    /// TCP connection, optionally exchanging a payload and a banner
+    Tcp,
+    /// DNS query for a record served by a nameserver
+    Dns,
+    /// TLS handshake verifying the certificate chain and its expiry
+    Tls,
+    /// Pings pushed by the monitored job to the web server
+    Heartbeat,
+    /// Local command expected to exit with code 0
+    Exec,
+    /// Free space and inodes of a local mount
+    Disk,
+    /// Age of a local file since its last modification
+    File,
+    /// Local process found by its name or PID file
+    Process,
+    /// Redis server answering `PING`
+    Redis,
+    /// PostgreSQL server accepting the startup handshake
+    Postgres,
+    /// WebSocket upgrade handshake, optionally exchanging a message
+    Websocket,
+    /// Standard gRPC health checking protocol
+    Grpc,
+}
+
+/// Type of the queried DNS record
+#[derive(Debug, Default, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DnsRecordType {
+    #[default]
+    A,
+    Aaaa,
+    Cname,
+    Mx,
+    Txt,
+}
+
+impl DnsRecordType {
+    /// Numeric value of the type used on the wire
+    pub fn code(&self) -> u16 {
+        match self {
+            DnsRecordType::A => 1,
+            DnsRecordType::Cname => 5,
+            DnsRecordType::Mx => 15,
+            DnsRecordType::Txt => 16,
+            DnsRecordType::Aaaa => 28,
+        }
+    }
+}
+
+impl std::fmt::Display for DnsRecordType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            DnsRecordType::A => "A",
+            DnsRecordType::Aaaa => "AAAA",
+            DnsRecordType::Cname => "CNAME",
+            DnsRecordType::Mx => "MX",
+            DnsRecordType::Txt => "TXT",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Transport used for DNS queries
+#[derive(Debug, Default, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsProtocol {
+    #[default]
+    Udp,
+    Tcp,
+}
+
+/// HTTP method used for the health check request
+#[derive(Debug, Default, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    #[default]
+    Get,
+    Head,
+    Post,
+    Put,
+    Options,
+}
+
+impl From<HttpMethod> for reqwest::Method {
+    fn from(method: HttpMethod) -> Self {
+        match method {
+            HttpMethod::Get => reqwest::Method::GET,
+            HttpMethod::Head => reqwest::Method::HEAD,
+            HttpMethod::Post => reqwest::Method::POST,
+            HttpMethod::Put => reqwest::Method::PUT,
+            HttpMethod::Options => reqwest::Method::OPTIONS,
+        }
+    }
+}
+
+impl ServiceConfig {
+    /// Unique identifier of the service used in configuration errors and dependencies
+    pub fn id(&self) -> String {
+        match (&self.id, self.check_type) {
+            (Some(id), _) => id.clone(),
+            (None, CheckType::Heartbeat) => heartbeat_id(self.token.as_deref().unwrap_or_default()),
+            (None, _) => self.target(),
+        }
+    }
+
+    /// Service in configuration errors, with the file it is defined in
+    pub fn describe(&self) -> String {
+        match &self.origin {
+            Some(origin) => format!("{} in {}", self.id(), origin),
+            None => self.id(),
+        }
+    }
+
+    /// Address of the monitored target, e.g. the url or `tcp://host:port`
+    pub fn target(&self) -> String {
+        match self.check_type {
+            CheckType::Http | CheckType::Websocket | CheckType::Grpc => {
+                self.url.clone().unwrap_or_default()
+            }
+            CheckType::Tcp => format!(
+                "tcp://{}:{}",
+                self.host.as_deref().unwrap_or_default(),
+                self.port.unwrap_or_default()
+            ),
+            CheckType::Dns => format!(
+                "dns://{}/{} ({})",
+                self.nameserver.as_deref().unwrap_or_default(),
+                self.domain.as_deref().unwrap_or_default(),
+                self.record_type
+            ),
+            CheckType::Tls => format!(
+                "tls://{}:{}",
+                self.host.as_deref().unwrap_or_default(),
+                self.port.unwrap_or(443)
+            ),
+            CheckType::Heartbeat => heartbeat_target(self.token.as_deref().unwrap_or_default()),
+            CheckType::Exec => format!("exec://{}", self.command.as_deref().unwrap_or_default()),
+            CheckType::Disk => {
+                format!("disk://{}", self.path.clone().unwrap_or_default().display())
+            }
+            CheckType::File => {
+                format!("file://{}", self.path.clone().unwrap_or_default().display())
+            }
+            CheckType::Process => match (&self.process_name, &self.pid_file) {
+                (Some(name), _) => format!("process://{}", name),
+                (None, pid_file) => format!(
+                    "process://{}",
+                    pid_file.clone().unwrap_or_default().display()
+                ),
+            },
+            CheckType::Redis => format!(
+                "redis://{}:{}",
+                self.host.as_deref().unwrap_or_default(),
+                self.port.unwrap_or(6379)
+            ),
+            CheckType::Postgres => format!(
+                "postgres://{}:{}",
+                self.host.as_deref().unwrap_or_default(),
+                self.port.unwrap_or(5432)
+            ),
+        }
+    }
+
+    fn resolve_secrets(&mut self) -> Result<(), String> {
+        resolve_optional_secret("token", &mut self.token, self.token_file.take())?;
+        resolve_optional_secret("password", &mut self.password, self.password_file.take())?;
+
+        for (name, value) in &mut self.headers {
+            *value = secret::interpolate(value).map_err(|e| format!("header {}: {}", name, e))?;
+        }
+        for (name, file) in std::mem::take(&mut self.header_files) {
+            if self.headers.contains_key(&name) {
+                return Err(format!(
+                    "header {} is set in both headers and header_files",
+                    name
+                ));
+            }
+            let value = secret::read_file(&file).map_err(|e| format!("header {}: {}", name, e))?;
+            self.headers.insert(name, value);
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if [&self.id, &self.name, &self.group]
+            .iter()
+            .any(|value| value.as_deref() == Some(""))
+        {
+            return Err("id, name and group can't be empty".to_string());
+        }
+        match self.check_type {
+            CheckType::Http | CheckType::Grpc if self.url.is_none() => {
+                return Err("url is required by http and grpc checks".to_string())
+            }
+            CheckType::Tcp if self.host.is_none() || self.port.is_none() => {
+                return Err("host and port are required by tcp checks".to_string())
+            }
+            CheckType::Dns if self.nameserver.is_none() || self.domain.is_none() => {
+                return Err("nameserver and domain are required by dns checks".to_string())
+            }
+            CheckType::Websocket
+                if !self
+                    .url
+                    .as_deref()
+                    .is_some_and(|url| url.starts_with("ws://") || url.starts_with("wss://")) =>
+            {
+                return Err("ws:// or wss:// url is required by websocket checks".to_string())
+            }
+            CheckType::Tls if self.host.is_none() => {
+                return Err("host is required by tls checks".to_string())
+            }
+            CheckType::Redis if self.host.is_none() => {
+                return Err("host is required by redis checks".to_string())
+            }
+            CheckType::Postgres if self.host.is_none() => {
+                return Err("host is required by postgres checks".to_string())
+            }
+            CheckType::Heartbeat if self.token.is_none() || self.period.is_none() => {
+                return Err("token and period are required by heartbeat checks".to_string())
+            }
+            CheckType::Exec if self.command.as_deref().unwrap_or_default().is_empty() => {
+                return Err("command is required by exec checks".to_string())
+            }
+            CheckType::Disk
+                if self.path.is_none()
+                    || (self.min_free_percent.is_none()
+                        && self.min_free_inodes_percent.is_none()) =>
+            {
+                return Err(
+                    "path and a free space threshold are required by disk checks".to_string(),
+                )
+            }
+            CheckType::File if self.path.is_none() || self.max_age.is_none() => {
+                return Err("path and max_age are required by file checks".to_string())
+            }
+            CheckType::Process if !cfg!(target_os = "linux") => {
+                return Err("process checks are supported only on Linux".to_string())
+            }
+            CheckType::Process if self.process_name.is_some() == self.pid_file.is_some() => {
+                return Err("process_name or pid_file is required by process checks".to_string())
+            }
+            _ => {}
+        }
+        if let Some(nameserver) = &self.nameserver {
+            if parse_nameserver(nameserver).is_none() {
+                return Err(format!(
+                    "nameserver {:?} has to be an IP address with an optional port",
+                    nameserver
+                ));
+            }
+        }
+        if let Some(days) = &self.expiry_warning_days {
+            if days.is_empty() || days.contains(&0) {
+                return Err("expiry_warning_days have to be greater than zero".to_string());
+            }
+        }
+        for assertion in &self.assertions {
+            assertion.validate()?;
+        }
+        for expression in &self.json_assertions {
+            JsonAssertion::from_str(expression)?;
+        }
+        for (name, value) in &self.headers {
+            reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("invalid header name {:?}", name))?;
+            reqwest::header::HeaderValue::from_str(value)
+                .map_err(|_| format!("invalid value of header {:?}", name))?;
+        }
+        if self.body.is_some() && self.json_body.is_some() {
+            return Err("body and json_body can't be used together".to_string());
+        }
+        if self.interval_seconds < Duration::from_secs(1) {
+            return Err("interval_seconds has to be at least a second".to_string());
+        }
+        if is_zero(self.timeout) || is_zero(self.connect_timeout) {
+            return Err("timeouts have to be greater than zero".to_string());
+        }
+        if let Some(token) = &self.token {
+            if token.is_empty()
+                || !token
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err("token may contain only letters, digits, '-' and '_'".to_string());
+            }
+        }
+        if [self.min_free_percent, self.min_free_inodes_percent]
+            .iter()
+            .flatten()
+            .any(|percent| *percent > 100)
+        {
+            return Err("free space thresholds are percentages up to 100".to_string());
+        }
+        if is_zero(self.max_age) {
+            return Err("max_age has to be greater than zero".to_string());
+        }
+        if is_zero(self.period) {
+            return Err("period has to be greater than zero".to_string());
+        }
+        if is_zero(self.max_retry_delay) {
+            return Err("max_retry_delay has to be greater than zero".to_string());
+        }
+        for maintenance in &self.maintenance {
+            MaintenanceWindow::try_from(maintenance)?;
+        }
+        if self.failure_threshold == Some(0) || self.recovery_threshold == Some(0) {
+            return Err("failure and recovery thresholds have to be at least 1".to_string());
+        }
+        if self.flap_threshold == Some(0) || is_zero(self.flap_window) {
+            return Err("flap_threshold and flap_window have to be greater than zero".to_string());
+        }
+        if is_zero(self.warn_latency_ms) || is_zero(self.critical_latency_ms) {
+            return Err("latency thresholds have to be greater than zero".to_string());
+        }
+        if let (Some(warn), Some(critical)) = (self.warn_latency_ms, self.critical_latency_ms) {
+            if warn >= critical {
+                return Err("warn_latency_ms has to be below critical_latency_ms".to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Assertion evaluated against the HTTP response of a service
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AssertionConfig {
+    /// Response body contains the given text
+    BodyContains { value: String },
+    /// Response body does not contain the given text
+    BodyNotContains { value: String },
+    /// Response body matches the given regular expression
+    BodyMatches { pattern: String },
+    /// Response header is present and optionally equal to the given value
+    Header { name: String, value: Option<String> },
+}
+
+impl AssertionConfig {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            AssertionConfig::BodyMatches { pattern } => regex::Regex::new(pattern)
+                .map(|_| ())
+                .map_err(|e| format!("invalid body_matches pattern {:?}: {}", pattern, e)),
+            AssertionConfig::Header { name, .. } => {
+                reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                    .map(|_| ())
+                    .map_err(|_| format!("invalid header name {:?}", name))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Inclusive range of accepted HTTP status codes.
+///
+/// Deserializes from a single code (`301` or `"301"`) or a range (`"200-299"`).
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(try_from = "StatusCodeValue")]
+pub struct StatusCodeRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl StatusCodeRange {
+    pub fn contains(&self, status: u16) -> bool {
+        (self.start..=self.end).contains(&status)
+    }
+
+    /// Whether any redirection code is within the range
+    pub fn is_redirect(&self) -> bool {
+        self.start <= 399 && self.end >= 300
+    }
+}
+
+impl FromStr for StatusCodeRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = match s.split_once('-') {
+            Some((start, end)) => (parse_status_code(start)?, parse_status_code(end)?),
+            None => {
+                let code = parse_status_code(s)?;
+                (code, code)
+            }
+        };
+
+        if start > end {
+            return Err(format!("invalid status code range {}", s));
+        }
+
+        Ok(Self { start, end })
+    }
+}
+
+impl TryFrom<StatusCodeValue> for StatusCodeRange {
+    type Error = String;
+
+    fn try_from(value: StatusCodeValue) -> Result<Self, Self::Error> {
+        match value {
+            StatusCodeValue::Code(code) => StatusCodeRange::from_str(&code.to_string()),
+            StatusCodeValue::Text(text) => StatusCodeRange::from_str(&text),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StatusCodeValue {
+    Code(i64),
+    Text(String),
+}
+
+fn parse_status_code(input: &str) -> Result<u16, String> {
+    match input.trim().parse::<u16>() {
+        Ok(code) if (100..=599).contains(&code) => Ok(code),
+        _ => Err(format!("{} is not a valid HTTP status code", input.trim())),
+    }
+}
+
+/// Default total request timeout
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default connection timeout
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default window of the flap detection
+pub const DEFAULT_FLAP_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Default days before certificate expiry when warnings are sent
+pub const DEFAULT_EXPIRY_WARNING_DAYS: [u32; 3] = [21, 7, 1];
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
 pub struct Fluxa {
     pub listen: String,
+    // Default total request timeout for all services
+    #[serde(default = "default_timeout", deserialize_with = "duration::seconds")]
+    pub timeout: Duration,
+    // Default connection timeout for all services
+    #[serde(
+        default = "default_connect_timeout",
+        deserialize_with = "duration::seconds"
+    )]
+    pub connect_timeout: Duration,
+    // Default notification about the first check result of all services
+    #[serde(default)]
+    pub initial_notification: InitialNotification,
+}
+
+impl Default for Fluxa {
+    fn default() -> Self {
+        Self {
+            listen: String::default(),
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            initial_notification: InitialNotification::default(),
+        }
+    }
+}
+
+fn default_timeout() -> Duration {
+    DEFAULT_TIMEOUT
+}
+
+fn default_connect_timeout() -> Duration {
+    DEFAULT_CONNECT_TIMEOUT
 }
 
 #[derive(Debug, Default, Deserialize, PartialEq, Eq, Clone)]
 pub struct TelegramConfig {
+    // Bot token, or the file it is read from - one of them is required
+    #[serde(default)]
     pub bot_token: String,
+    pub bot_token_file: Option<PathBuf>,
     pub chat_id: String,
 }
 
@@ -30,35 +712,489 @@ pub struct FluxaConfig {
     pub pushover_api_key: String,
     #[serde(default)]
     pub pushover_user_key: String,
+    // Files the Pushover keys are read from instead (optional)
+    pub pushover_api_key_file: Option<PathBuf>,
+    pub pushover_user_key_file: Option<PathBuf>,
 
     // Telegram config (optional) - structured section
     pub telegram: Option<TelegramConfig>,
 
+    // Maintenance windows of all services (optional)
+    #[serde(default)]
+    pub maintenance: Vec<MaintenanceConfig>,
+
+    // Deserialized after merging `[defaults]` and `[templates.<name>]` into every service
+    #[serde(skip)]
     pub services: Vec<ServiceConfig>,
     pub fluxa: Fluxa,
 }
 impl FluxaConfig {
+    /// Load the configuration file, or every `*.toml` file of a directory in
+    /// name order, with the services of the files it `include`s
     pub fn new(path: &Path) -> Result<Self, ServiceConfigurationError> {
-        let settings = Config::builder()
-            .add_source(File::from(path))
-            .build()
-            .map_err(|e| {
-                ServiceConfigurationError::ErrorInConfiguration(format!(
-                    "Failed to build config from path {:?}: {}",
-                    path, e
-                ))
-            })?;
+        let files = if path.is_dir() {
+            directory_files(path)?
+        } else {
+            vec![path.to_path_buf()]
+        };
+        let mut loaded: HashSet<PathBuf> = files.iter().map(|file| canonical(file)).collect();
+
+        let mut builder = Config::builder();
+        let mut services = Vec::new();
+        for file in &files {
+            let settings = load_file(file)?;
+            services.extend(optional_array(&settings, "services")?);
+
+            for included in included_files(&settings, file)? {
+                if !loaded.insert(canonical(&included)) {
+                    continue;
+                }
+                let included_settings = load_file(&included)?;
+                if let Some(key) = included_settings
+                    .collect()?
+                    .keys()
+                    .find(|key| *key != "services")
+                {
+                    return Err(ServiceConfigurationError::ErrorInConfiguration(format!(
+                        "Included file {:?} can only define services, found {}",
+                        included, key
+                    )));
+                }
+                services.extend(optional_array(&included_settings, "services")?);
+            }
+            builder = builder.add_source(settings);
+        }
+
+        if services.is_empty() {
+            return Err(ServiceConfigurationError::ErrorInConfiguration(format!(
+                "No services are configured in {:?}",
+                path
+            )));
+        }
+        let settings = builder.build()?;
 
-        Self::build(settings)
+        Self::build(settings, services)
     }
 
-    pub(super) fn build(settings: Config) -> Result<Self, ServiceConfigurationError> {
+    pub(super) fn build(
+        settings: Config,
+        services: Vec<Value>,
+    ) -> Result<Self, ServiceConfigurationError> {
+        let services = Self::merge_services(&settings, services)?;
         let result: Result<FluxaConfig, ConfigError> = settings.try_deserialize();
         match result {
-            Ok(config) => Ok(config),
+            Ok(mut config) => {
+                config.services = services;
+                config.resolve_secrets()?;
+                config.validate()?;
+                config.apply_defaults();
+                Ok(config)
+            }
             Err(err) => Err(ServiceConfigurationError::from(err)),
         }
     }
+
+    /// Deserialize the services, each merged field by field over the template
+    /// it `extends` and the `[defaults]` section, values of the service win
+    fn merge_services(
+        settings: &Config,
+        services: Vec<Value>,
+    ) -> Result<Vec<ServiceConfig>, ServiceConfigurationError> {
+        let defaults = optional_table(settings, "defaults")?;
+        let templates = optional_table(settings, "templates")?;
+
+        services
+            .into_iter()
+            .enumerate()
+            .map(|(index, service)| {
+                let origin = service.origin().map(str::to_string);
+                let service = service.into_table()?;
+                let name = match &origin {
+                    Some(origin) => format!("{} in {}", describe_service(&service, index), origin),
+                    None => describe_service(&service, index),
+                };
+                let error = |e: String| {
+                    ServiceConfigurationError::ErrorInConfiguration(format!(
+                        "Service {}: {}",
+                        name, e
+                    ))
+                };
+
+                let mut merged = defaults.clone();
+                if let Some(extends) = service.get("extends") {
+                    let template_name = extends
+                        .clone()
+                        .into_string()
+                        .map_err(|e| error(e.to_string()))?;
+                    let template = templates
+                        .get(&template_name)
+                        .ok_or_else(|| error(format!("unknown template {:?}", template_name)))?
+                        .clone()
+                        .into_table()
+                        .map_err(|e| error(e.to_string()))?;
+                    if template.contains_key("extends") {
+                        return Err(error(format!(
+                            "template {:?} can't extend another template",
+                            template_name
+                        )));
+                    }
+                    merge_table(&mut merged, template);
+                }
+                merge_table(&mut merged, service);
+                merged.remove("extends");
+
+                let mut service = Value::new(None, merged)
+                    .try_deserialize::<ServiceConfig>()
+                    .map_err(|e| error(e.to_string()))?;
+                service.origin = origin;
+                Ok(service)
+            })
+            .collect()
+    }
+
+    /// Replace `${NAME}` in secrets and header values by environment variables
+    /// and read the `*_file` variants, the `*_file` fields are emptied
+    fn resolve_secrets(&mut self) -> Result<(), ServiceConfigurationError> {
+        let error = ServiceConfigurationError::ErrorInConfiguration;
+
+        secret::resolve(
+            "pushover_api_key",
+            &mut self.pushover_api_key,
+            self.pushover_api_key_file.take().as_deref(),
+        )
+        .map_err(error)?;
+        secret::resolve(
+            "pushover_user_key",
+            &mut self.pushover_user_key,
+            self.pushover_user_key_file.take().as_deref(),
+        )
+        .map_err(error)?;
+
+        if let Some(telegram) = &mut self.telegram {
+            secret::resolve(
+                "telegram bot_token",
+                &mut telegram.bot_token,
+                telegram.bot_token_file.take().as_deref(),
+            )
+            .map_err(error)?;
+            if telegram.bot_token.is_empty() {
+                return Err(error(
+                    "telegram bot_token or bot_token_file is required".to_string(),
+                ));
+            }
+        }
+
+        for service in &mut self.services {
+            service
+                .resolve_secrets()
+                .map_err(|e| error(format!("Service {}: {}", service.describe(), e)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Fill in service values which fall back to the `[fluxa]` section
+    fn apply_defaults(&mut self) {
+        for service in &mut self.services {
+            service.timeout.get_or_insert(self.fluxa.timeout);
+            service
+                .connect_timeout
+                .get_or_insert(self.fluxa.connect_timeout);
+            service
+                .initial_notification
+                .get_or_insert(self.fluxa.initial_notification);
+            service.maintenance.extend(self.maintenance.iter().cloned());
+        }
+    }
+
+    /// Validate values which can't be checked by deserialization alone
+    fn validate(&self) -> Result<(), ServiceConfigurationError> {
+        if self.fluxa.timeout.is_zero() || self.fluxa.connect_timeout.is_zero() {
+            return Err(ServiceConfigurationError::ErrorInConfiguration(
+                "Fluxa timeouts have to be greater than zero".to_string(),
+            ));
+        }
+
+        for maintenance in &self.maintenance {
+            MaintenanceWindow::try_from(maintenance)
+                .map_err(ServiceConfigurationError::ErrorInConfiguration)?;
+        }
+
+        let mut ids = HashMap::new();
+        let mut tokens = HashSet::new();
+        for service in &self.services {
+            service.validate().map_err(|e| {
+                ServiceConfigurationError::ErrorInConfiguration(format!(
+                    "Service {}: {}",
+                    service.describe(),
+                    e
+                ))
+            })?;
+            if let Some(other) = ids.insert(service.id(), service) {
+                let location = match &other.origin {
+                    Some(origin) => format!(" in {}", origin),
+                    None => String::new(),
+                };
+                return Err(ServiceConfigurationError::ErrorInConfiguration(format!(
+                    "Service {}: id is already used by another service{}, set a unique id",
+                    service.describe(),
+                    location
+                )));
+            }
+            if let Some(token) = &service.token {
+                if !tokens.insert(token) {
+                    return Err(ServiceConfigurationError::ErrorInConfiguration(format!(
+                        "Service {}: token is already used by another service",
+                        service.describe()
+                    )));
+                }
+            }
+        }
+
+        self.validate_dependencies()
+            .map_err(ServiceConfigurationError::ErrorInConfiguration)
+    }
+
+    /// Verify that services depend only on configured services and never on
+    /// themselves, directly or through other services
+    fn validate_dependencies(&self) -> Result<(), String> {
+        let dependencies: HashMap<String, &Vec<String>> = self
+            .services
+            .iter()
+            .map(|service| (service.id(), &service.depends_on))
+            .collect();
+
+        for service in &self.services {
+            if let Some(parent) = service
+                .depends_on
+                .iter()
+                .find(|parent| !dependencies.contains_key(*parent))
+            {
+                return Err(format!(
+                    "Service {}: depends on unknown service {}",
+                    service.describe(),
+                    parent
+                ));
+            }
+        }
+
+        let mut finished = HashSet::new();
+        for service in &self.services {
+            if let Some((target, _)) = dependencies.get_key_value(&service.id()) {
+                visit_dependencies(target, &dependencies, &mut Vec::new(), &mut finished)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolve a secret which may be missing along with its `<name>_file` variant
+fn resolve_optional_secret(
+    name: &str,
+    value: &mut Option<String>,
+    file: Option<PathBuf>,
+) -> Result<(), String> {
+    if value.is_none() && file.is_none() {
+        return Ok(());
+    }
+    let mut secret = value.take().unwrap_or_default();
+    secret::resolve(name, &mut secret, file.as_deref())?;
+    *value = Some(secret);
+
+    Ok(())
+}
+
+fn is_zero(duration: Option<Duration>) -> bool {
+    duration.is_some_and(|duration| duration.is_zero())
+}
+
+/// Parse a single configuration file
+fn load_file(path: &Path) -> Result<Config, ServiceConfigurationError> {
+    Config::builder()
+        .add_source(File::from(path))
+        .build()
+        .map_err(|e| {
+            ServiceConfigurationError::ErrorInConfiguration(format!(
+                "Failed to build config from path {:?}: {}",
+                path, e
+            ))
+        })
+}
+
+/// Configuration files of a directory, sorted by name
+fn directory_files(directory: &Path) -> Result<Vec<PathBuf>, ServiceConfigurationError> {
+    let error = |e: std::io::Error| {
+        ServiceConfigurationError::ErrorInConfiguration(format!(
+            "Failed to read config directory {:?}: {}",
+            directory, e
+        ))
+    };
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(directory).map_err(error)? {
+        let path = entry.map_err(error)?.path();
+        if path.is_file()
+            && path
+                .extension()
+                .is_some_and(|extension| extension == "toml")
+        {
+            files.push(path);
+        }
+    }
+    if files.is_empty() {
+        return Err(ServiceConfigurationError::ErrorInConfiguration(format!(
+            "No *.toml files in config directory {:?}",
+            directory
+        )));
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+/// Files matching the `include` globs of a configuration file, relative
+/// patterns start in the directory of the including file
+fn included_files(
+    settings: &Config,
+    file: &Path,
+) -> Result<Vec<PathBuf>, ServiceConfigurationError> {
+    let patterns: Vec<String> = match settings.get("include") {
+        Ok(patterns) => patterns,
+        Err(ConfigError::NotFound(_)) => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let base = file.parent().unwrap_or(Path::new(""));
+
+    let mut files = Vec::new();
+    for pattern in patterns {
+        let full_pattern = base.join(&pattern);
+        let paths = glob::glob(&full_pattern.to_string_lossy()).map_err(|e| {
+            ServiceConfigurationError::ErrorInConfiguration(format!(
+                "Invalid include {:?} in {:?}: {}",
+                pattern, file, e
+            ))
+        })?;
+
+        let count = files.len();
+        for path in paths {
+            let path = path.map_err(|e| {
+                ServiceConfigurationError::ErrorInConfiguration(format!(
+                    "Failed to include {:?}: {}",
+                    e.path(),
+                    e.error()
+                ))
+            })?;
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+        if files.len() == count {
+            warn!("Include {:?} in {:?} matches no files", pattern, file);
+        }
+    }
+
+    Ok(files)
+}
+
+/// Path used to recognize a file loaded twice
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Array of the configuration, empty when the key is missing
+fn optional_array(settings: &Config, key: &str) -> Result<Vec<Value>, ServiceConfigurationError> {
+    match settings.get_array(key) {
+        Ok(array) => Ok(array),
+        Err(ConfigError::NotFound(_)) => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Table of the configuration, empty when the section is missing
+fn optional_table(
+    settings: &Config,
+    key: &str,
+) -> Result<Map<String, Value>, ServiceConfigurationError> {
+    match settings.get_table(key) {
+        Ok(table) => Ok(table),
+        Err(ConfigError::NotFound(_)) => Ok(Map::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Merge the values over the base, nested tables like `headers` are merged
+/// key by key while other values are replaced
+fn merge_table(base: &mut Map<String, Value>, values: Map<String, Value>) {
+    for (key, value) in values {
+        match (base.get_mut(&key), value) {
+            (
+                Some(Value {
+                    kind: ValueKind::Table(base_table),
+                    ..
+                }),
+                Value {
+                    kind: ValueKind::Table(table),
+                    ..
+                },
+            ) => merge_table(base_table, table),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Identify a service in errors before it is deserialized, by its id, name
+/// or monitored target when present, a heartbeat token is never shown whole
+fn describe_service(service: &Map<String, Value>, index: usize) -> String {
+    let value = |key: &str| {
+        service
+            .get(key)
+            .and_then(|value| value.clone().into_string().ok())
+    };
+
+    ["id", "name", "url", "host"]
+        .into_iter()
+        .find_map(value)
+        .or_else(|| value("token").map(|token| heartbeat_target(&token)))
+        .or_else(|| {
+            ["command", "path", "process_name"]
+                .into_iter()
+                .find_map(value)
+        })
+        .unwrap_or_else(|| format!("#{}", index + 1))
+}
+
+/// Depth first search of the dependencies, a service met again on the
+/// current path closes a cycle
+fn visit_dependencies<'a>(
+    target: &'a str,
+    dependencies: &'a HashMap<String, &'a Vec<String>>,
+    path: &mut Vec<&'a str>,
+    finished: &mut HashSet<&'a str>,
+) -> Result<(), String> {
+    if finished.contains(target) {
+        return Ok(());
+    }
+    if let Some(position) = path.iter().position(|visited| *visited == target) {
+        let mut cycle = path[position..].to_vec();
+        cycle.push(target);
+        return Err(format!("Dependency cycle {}", cycle.join(" -> ")));
+    }
+
+    path.push(target);
+    for parent in dependencies
+        .get(target)
+        .into_iter()
+        .flat_map(|parents| parents.iter())
+    {
+        visit_dependencies(parent, dependencies, path, finished)?;
+    }
+    path.pop();
+    finished.insert(target);
+
+    Ok(())
 }
 
 impl FromStr for FluxaConfig {
@@ -74,8 +1210,14 @@ impl FromStr for FluxaConfig {
                     e
                 ))
             })?;
+        if settings.get::<Value>("include").is_ok() {
+            return Err(ServiceConfigurationError::ErrorInConfiguration(
+                "include is supported only in configuration files".to_string(),
+            ));
+        }
+        let services = settings.get_array("services")?;
 
-        Self::build(settings)
+        Self::build(settings, services)
     }
 }
 
@@ -129,6 +1271,1124 @@ retry_interval = 3
         }
     }
 
+    #[test]
+    fn test_expected_status_codes_and_ranges() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+url = "http://localhost:3000"
+interval_seconds = 300
+max_retries = 3
+retry_interval = 3
+expected_status = ["200-299", 301, "401"]
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+
+        assert_eq!(
+            config.services[0].expected_status,
+            vec![
+                StatusCodeRange {
+                    start: 200,
+                    end: 299
+                },
+                StatusCodeRange {
+                    start: 301,
+                    end: 301
+                },
+                StatusCodeRange {
+                    start: 401,
+                    end: 401
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_expected_status_is_rejected() {
+        for invalid in [r#""299-200""#, "42", r#""2xx""#] {
+            let fluxa_configuration = format!(
+                r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+url = "http://localhost:3000"
+interval_seconds = 300
+max_retries = 3
+retry_interval = 3
+expected_status = [{}]
+                "#,
+                invalid
+            );
+
+            assert!(
+                FluxaConfig::from_str(&fluxa_configuration).is_err(),
+                "{} should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_response_assertions() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+url = "http://localhost:3000"
+interval_seconds = 300
+max_retries = 3
+retry_interval = 3
+assertions = [
+  { type = "body_not_contains", value = "Maintenance" },
+  { type = "body_matches", pattern = "version: \\d+" },
+  { type = "header", name = "content-type", value = "text/html" },
+  { type = "header", name = "x-served-by" },
+]
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+
+        assert_eq!(
+            config.services[0].assertions,
+            vec![
+                AssertionConfig::BodyNotContains {
+                    value: "Maintenance".to_string()
+                },
+                AssertionConfig::BodyMatches {
+                    pattern: "version: \\d+".to_string()
+                },
+                AssertionConfig::Header {
+                    name: "content-type".to_string(),
+                    value: Some("text/html".to_string())
+                },
+                AssertionConfig::Header {
+                    name: "x-served-by".to_string(),
+                    value: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_body_pattern_is_rejected() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+url = "http://localhost:3000"
+interval_seconds = 300
+max_retries = 3
+retry_interval = 3
+assertions = [{ type = "body_matches", pattern = "(unclosed" }]
+        "#;
+        let result = FluxaConfig::from_str(fluxa_configuration);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_invalid_json_assertion_is_rejected() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+url = "http://localhost:3000/healthz"
+interval_seconds = 300
+max_retries = 3
+retry_interval = 3
+json_assertions = ['$.status == "ok"', "$.db = up"]
+        "#;
+        let result = FluxaConfig::from_str(fluxa_configuration);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_request_method_headers_and_body() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+url = "http://localhost:3000/api/health"
+interval_seconds = 300
+max_retries = 3
+retry_interval = 3
+method = "POST"
+headers = { Authorization = "Bearer token", "User-Agent" = "fluxa" }
+json_body = { probe = true, depth = 2 }
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+        let service = &config.services[0];
+
+        assert_eq!(service.method, HttpMethod::Post);
+        assert_eq!(
+            service.headers.get("User-Agent"),
+            Some(&"fluxa".to_string())
+        );
+        assert_eq!(
+            service.json_body,
+            Some(serde_json::json!({ "probe": true, "depth": 2 }))
+        );
+    }
+
+    #[test]
+    fn test_secrets_from_environment_and_files() {
+        let token_file =
+            std::env::temp_dir().join(format!("fluxa-{}-bot-token", std::process::id()));
+        std::fs::write(&token_file, "123:telegram-token\n").unwrap();
+        std::env::set_var("FLUXA_SETTINGS_PUSHOVER", "pushover-key");
+        std::env::set_var("FLUXA_SETTINGS_API_TOKEN", "api-token");
+
+        let fluxa_configuration = format!(
+            r#"
+pushover_api_key = "${{FLUXA_SETTINGS_PUSHOVER}}"
+pushover_user_key = "user"
+
+[telegram]
+bot_token_file = {:?}
+chat_id = "42"
+
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+url = "http://localhost:3000"
+interval_seconds = 300
+max_retries = 3
+retry_interval = 3
+headers = {{ Authorization = "Bearer ${{FLUXA_SETTINGS_API_TOKEN}}" }}
+header_files = {{ "X-Api-Key" = {:?} }}
+            "#,
+            token_file, token_file
+        );
+        let config = FluxaConfig::from_str(&fluxa_configuration).unwrap();
+        let headers = &config.services[0].headers;
+
+        assert_eq!(config.pushover_api_key, "pushover-key");
+        assert_eq!(config.telegram.unwrap().bot_token, "123:telegram-token");
+        assert_eq!(
+            headers.get("Authorization"),
+            Some(&"Bearer api-token".to_string())
+        );
+        assert_eq!(
+            headers.get("X-Api-Key"),
+            Some(&"123:telegram-token".to_string())
+        );
+
+        let missing =
+            fluxa_configuration.replace("FLUXA_SETTINGS_API_TOKEN", "FLUXA_SETTINGS_UNSET");
+        assert_eq!(
+            FluxaConfig::from_str(&missing).unwrap_err().to_string(),
+            "Configuration error Service http://localhost:3000: header Authorization: \
+             environment variable FLUXA_SETTINGS_UNSET is not set"
+        );
+
+        let both = fluxa_configuration.replace("[telegram]", "[telegram]\nbot_token = \"inline\"");
+        let error = FluxaConfig::from_str(&both).unwrap_err().to_string();
+        assert!(error.contains("bot_token_file"), "{}", error);
+        assert!(!error.contains("123:telegram-token"), "{}", error);
+
+        std::fs::remove_file(&token_file).unwrap();
+        let error = FluxaConfig::from_str(&fluxa_configuration)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("telegram bot_token: failed to read"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_body_and_json_body_are_exclusive() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+url = "http://localhost:3000/api/health"
+interval_seconds = 300
+max_retries = 3
+retry_interval = 3
+method = "PUT"
+body = "ping"
+json_body = { probe = true }
+        "#;
+        let result = FluxaConfig::from_str(fluxa_configuration);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_timeouts_fall_back_to_fluxa_defaults() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+timeout = 20
+
+[[services]]
+url = "http://localhost:3000"
+interval_seconds = 300
+max_retries = 3
+retry_interval = 3
+
+[[services]]
+url = "http://localhost:4000"
+interval_seconds = 300
+max_retries = 3
+retry_interval = 3
+timeout = 5
+connect_timeout = 2
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+
+        assert_eq!(config.services[0].timeout, Some(Duration::from_secs(20)));
+        assert_eq!(
+            config.services[0].connect_timeout,
+            Some(DEFAULT_CONNECT_TIMEOUT)
+        );
+        assert_eq!(config.services[1].timeout, Some(Duration::from_secs(5)));
+        assert_eq!(
+            config.services[1].connect_timeout,
+            Some(Duration::from_secs(2))
+        );
+    }
+
+    #[test]
+    fn test_human_readable_durations() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+timeout = "1m"
+
+[[services]]
+url = "http://localhost:3000"
+interval_seconds = "1h30m"
+max_retries = 3
+retry_interval = "500ms"
+warn_latency_ms = "1s"
+critical_latency_ms = 2500
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+        let service = &config.services[0];
+
+        assert_eq!(config.fluxa.timeout, Duration::from_secs(60));
+        assert_eq!(service.interval_seconds, Duration::from_secs(5400));
+        assert_eq!(service.retry_interval, Duration::from_millis(500));
+        assert_eq!(service.warn_latency_ms, Some(Duration::from_secs(1)));
+        assert_eq!(
+            service.critical_latency_ms,
+            Some(Duration::from_millis(2500))
+        );
+
+        for invalid in [
+            r#"interval_seconds = "0s""#,
+            r#"interval_seconds = "500ms""#,
+            r#"interval_seconds = "90 minutes""#,
+            r#"interval_seconds = "400d""#,
+            "interval_seconds = -5",
+        ] {
+            let invalid = fluxa_configuration.replace(r#"interval_seconds = "1h30m""#, invalid);
+            assert!(FluxaConfig::from_str(&invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_defaults_and_templates_are_merged() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[defaults]
+interval_seconds = 300
+max_retries = 3
+retry_interval = 3
+headers = { "User-Agent" = "fluxa" }
+
+[templates.critical]
+interval_seconds = 30
+backoff = "exponential"
+group = "production"
+
+[[services]]
+url = "http://localhost:3000"
+
+[[services]]
+name = "API"
+extends = "critical"
+url = "http://localhost:4000"
+max_retries = 5
+headers = { Authorization = "Bearer token" }
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+        let (plain, critical) = (&config.services[0], &config.services[1]);
+
+        assert_eq!(
+            (
+                plain.interval_seconds,
+                plain.max_retries,
+                plain.retry_interval
+            ),
+            (Duration::from_secs(300), 3, Duration::from_secs(3))
+        );
+        assert_eq!(plain.backoff, Backoff::Fixed);
+
+        assert_eq!(
+            (
+                critical.interval_seconds,
+                critical.max_retries,
+                critical.retry_interval
+            ),
+            (Duration::from_secs(30), 5, Duration::from_secs(3))
+        );
+        assert_eq!(critical.backoff, Backoff::Exponential);
+        assert_eq!(critical.group.as_deref(), Some("production"));
+        assert_eq!(critical.headers.len(), 2);
+
+        let unknown = fluxa_configuration.replace("extends = \"critical\"", "extends = \"batch\"");
+        assert_eq!(
+            FluxaConfig::from_str(&unknown).unwrap_err().to_string(),
+            "Configuration error Service API: unknown template \"batch\""
+        );
+
+        let missing = fluxa_configuration.replace("interval_seconds = 300\n", "");
+        let error = FluxaConfig::from_str(&missing).unwrap_err().to_string();
+        assert!(
+            error.starts_with("Configuration error Service http://localhost:3000: "),
+            "{}",
+            error
+        );
+        assert!(error.contains("interval_seconds"), "{}", error);
+    }
+
+    /// Write the files into a new temporary directory
+    fn config_directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("fluxa-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&directory);
+        for (file, content) in files {
+            let path = directory.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn test_included_service_files() {
+        let service = |url: &str| {
+            format!(
+                "[[services]]\nurl = \"{}\"\ninterval_seconds = 60\nmax_retries = 3\nretry_interval = 3\n",
+                url
+            )
+        };
+        let main = format!(
+            "include = [\"services.d/*.toml\"]\n\n[fluxa]\nlisten = \"127.0.0.1:8080\"\n\n{}",
+            service("http://localhost:3000")
+        );
+        let team_a = service("http://localhost:4000") + &service("http://localhost:4001");
+        let team_b = service("http://localhost:5000");
+        let directory = config_directory(
+            "includes",
+            &[
+                ("config.toml", &main),
+                ("services.d/team-a.toml", &team_a),
+                ("services.d/team-b.toml", &team_b),
+            ],
+        );
+
+        let config = FluxaConfig::new(&directory.join("config.toml")).unwrap();
+        let targets: Vec<String> = config.services.iter().map(|s| s.target()).collect();
+        assert_eq!(
+            targets,
+            [
+                "http://localhost:3000",
+                "http://localhost:4000",
+                "http://localhost:4001",
+                "http://localhost:5000"
+            ]
+        );
+        assert!(config.services[3]
+            .origin
+            .as_deref()
+            .is_some_and(|origin| origin.ends_with("services.d/team-b.toml")));
+
+        fs::write(
+            directory.join("services.d/team-b.toml"),
+            service("http://localhost:4001"),
+        )
+        .unwrap();
+        let error = FluxaConfig::new(&directory.join("config.toml"))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("Service http://localhost:4001 in ")
+                && error.contains("team-b.toml: id is already used by another service in ")
+                && error.contains("team-a.toml"),
+            "{}",
+            error
+        );
+
+        fs::write(
+            directory.join("services.d/team-b.toml"),
+            "[[services]]\nurl = \"http://localhost:5000\"\n",
+        )
+        .unwrap();
+        let error = FluxaConfig::new(&directory.join("config.toml"))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("team-b.toml"), "{}", error);
+        assert!(error.contains("interval_seconds"), "{}", error);
+
+        fs::write(
+            directory.join("services.d/team-b.toml"),
+            "[fluxa]\nlisten = \"0.0.0.0:80\"\n",
+        )
+        .unwrap();
+        assert!(FluxaConfig::new(&directory.join("config.toml")).is_err());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_config_directory() {
+        let directory = config_directory(
+            "conf.d",
+            &[
+                ("00-fluxa.toml", "[fluxa]\nlisten = \"127.0.0.1:8080\"\n\n[defaults]\ninterval_seconds = 60\nmax_retries = 3\nretry_interval = 3\n"),
+                ("10-web.toml", "[[services]]\nurl = \"http://localhost:3000\"\n"),
+                ("20-db.toml", "[[services]]\ntype = \"tcp\"\nhost = \"localhost\"\nport = 5432\n"),
+                ("README.md", "not a configuration file"),
+            ],
+        );
+
+        let config = FluxaConfig::new(&directory).unwrap();
+        assert_eq!(config.fluxa.listen, "127.0.0.1:8080");
+        assert_eq!(config.services.len(), 2);
+        assert_eq!(config.services[1].target(), "tcp://localhost:5432");
+
+        fs::remove_dir_all(&directory).unwrap();
+        assert!(FluxaConfig::new(&directory).is_err());
+    }
+
+    #[test]
+    fn test_service_identity_and_unique_ids() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+id = "api-status"
+name = "API status"
+group = "production"
+tags = ["api", "public"]
+url = "http://localhost:3000/health"
+interval_seconds = 60
+max_retries = 0
+retry_interval = 1
+expected_status = [200]
+
+[[services]]
+id = "api-body"
+url = "http://localhost:3000/health"
+interval_seconds = 60
+max_retries = 0
+retry_interval = 1
+json_assertions = ["$.status == \"ok\""]
+depends_on = ["api-status"]
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+        let service = &config.services[0];
+
+        assert_eq!(service.id(), "api-status");
+        assert_eq!(service.name.as_deref(), Some("API status"));
+        assert_eq!(service.group.as_deref(), Some("production"));
+        assert_eq!(service.tags, ["api", "public"]);
+
+        let duplicate = fluxa_configuration.replace("id = \"api-body\"\n", "");
+        assert!(FluxaConfig::from_str(&duplicate).is_ok());
+        let duplicate = fluxa_configuration.replace("\"api-body\"", "\"api-status\"");
+        assert_eq!(
+            FluxaConfig::from_str(&duplicate).unwrap_err().to_string(),
+            "Configuration error Service api-status: id is already used by another service, set a unique id"
+        );
+    }
+
+    #[test]
+    fn test_dependency_cycles_are_rejected() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+url = "http://gateway"
+interval_seconds = 60
+max_retries = 0
+retry_interval = 1
+
+[[services]]
+url = "http://api"
+interval_seconds = 60
+max_retries = 0
+retry_interval = 1
+depends_on = ["http://gateway"]
+
+[[services]]
+type = "tcp"
+host = "db"
+port = 5432
+interval_seconds = 60
+max_retries = 0
+retry_interval = 1
+depends_on = ["http://api"]
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+        assert_eq!(config.services[2].depends_on, ["http://api"]);
+
+        let cycle = fluxa_configuration.replace(
+            "url = \"http://gateway\"\n",
+            "url = \"http://gateway\"\ndepends_on = [\"tcp://db:5432\"]\n",
+        );
+        assert_eq!(
+            FluxaConfig::from_str(&cycle).unwrap_err().to_string(),
+            "Configuration error Dependency cycle http://gateway -> tcp://db:5432 -> http://api -> http://gateway"
+        );
+
+        let unknown = fluxa_configuration.replace("[\"http://api\"]", "[\"http://cache\"]");
+        assert!(FluxaConfig::from_str(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_global_and_service_maintenance() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[maintenance]]
+cron = "0 2 * * Sun"
+duration = 7200
+timezone = "Europe/Prague"
+
+[[services]]
+url = "http://localhost:3000"
+interval_seconds = 300
+max_retries = 3
+retry_interval = 3
+maintenance = [
+  { start = "2025-06-01T22:00:00Z", end = "2025-06-02T01:00:00Z" },
+  { weekdays = ["mon", "thu"], from = "06:00", to = "06:30" },
+]
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+        let maintenance = &config.services[0].maintenance;
+
+        assert_eq!(maintenance.len(), 3);
+        assert_eq!(maintenance[1].weekdays, ["mon", "thu"]);
+        assert_eq!(maintenance[2].cron.as_deref(), Some("0 2 * * Sun"));
+
+        let invalid = fluxa_configuration.replace("06:30", "6:30pm");
+        assert!(FluxaConfig::from_str(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_initial_notification_policy() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+initial_notification = "silent"
+
+[[services]]
+url = "http://localhost:3000"
+interval_seconds = 300
+max_retries = 3
+retry_interval = 3
+
+[[services]]
+url = "http://localhost:4000"
+interval_seconds = 300
+max_retries = 3
+retry_interval = 3
+initial_notification = "always"
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+
+        assert_eq!(
+            config.services[0].initial_notification,
+            Some(InitialNotification::Silent)
+        );
+        assert_eq!(
+            config.services[1].initial_notification,
+            Some(InitialNotification::Always)
+        );
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+url = "http://localhost:3000"
+interval_seconds = 300
+max_retries = 5
+retry_interval = 2
+backoff = "exponential"
+retry_jitter = true
+max_retry_delay = 30
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+        let service = &config.services[0];
+
+        assert_eq!(service.backoff, Backoff::Exponential);
+        assert!(service.retry_jitter);
+        assert_eq!(service.max_retry_delay, Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_failure_recovery_and_flap_thresholds() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+url = "http://localhost:3000"
+interval_seconds = 60
+max_retries = 1
+retry_interval = 2
+failure_threshold = 3
+recovery_threshold = 2
+flap_threshold = 4
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+        let service = &config.services[0];
+
+        assert_eq!(service.failure_threshold, Some(3));
+        assert_eq!(service.recovery_threshold, Some(2));
+        assert_eq!(service.flap_threshold, Some(4));
+        assert_eq!(service.flap_window, None);
+
+        let disabled =
+            fluxa_configuration.replace("recovery_threshold = 2", "recovery_threshold = 0");
+        assert!(FluxaConfig::from_str(&disabled).is_err());
+    }
+
+    #[test]
+    fn test_latency_thresholds() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+url = "http://localhost:3000"
+interval_seconds = 300
+max_retries = 0
+retry_interval = 2
+warn_latency_ms = 800
+critical_latency_ms = 3000
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+        assert_eq!(
+            config.services[0].warn_latency_ms,
+            Some(Duration::from_millis(800))
+        );
+        assert_eq!(
+            config.services[0].critical_latency_ms,
+            Some(Duration::from_secs(3))
+        );
+
+        let inverted = fluxa_configuration.replace("= 800", "= 5000");
+        assert!(FluxaConfig::from_str(&inverted).is_err());
+    }
+
+    #[test]
+    fn test_tcp_service() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+type = "tcp"
+host = "localhost"
+port = 6379
+send = "PING\r\n"
+expect = "+PONG"
+interval_seconds = 60
+max_retries = 3
+retry_interval = 3
+
+[[services]]
+type = "tcp"
+host = "localhost"
+interval_seconds = 60
+max_retries = 3
+retry_interval = 3
+        "#;
+        let result = FluxaConfig::from_str(fluxa_configuration);
+
+        assert!(result.is_err());
+
+        let config = FluxaConfig::from_str(&fluxa_configuration.replace(
+            "host = \"localhost\"\ninterval",
+            "host = \"localhost\"\nport = 22\ninterval",
+        ))
+        .unwrap();
+        let service = &config.services[0];
+
+        assert_eq!(service.check_type, CheckType::Tcp);
+        assert_eq!(service.target(), "tcp://localhost:6379");
+        assert_eq!(service.send, Some("PING\r\n".to_string()));
+        assert_eq!(config.services[1].port, Some(22));
+    }
+
+    #[test]
+    fn test_dns_service() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+type = "dns"
+nameserver = "192.0.2.53"
+domain = "example.com"
+record_type = "MX"
+dns_protocol = "tcp"
+expected_records = ["10 mail.example.com"]
+interval_seconds = 60
+max_retries = 3
+retry_interval = 3
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+        let service = &config.services[0];
+
+        assert_eq!(service.check_type, CheckType::Dns);
+        assert_eq!(service.record_type, DnsRecordType::Mx);
+        assert_eq!(service.dns_protocol, DnsProtocol::Tcp);
+        assert_eq!(service.target(), "dns://192.0.2.53/example.com (MX)");
+
+        let result =
+            FluxaConfig::from_str(&fluxa_configuration.replace("192.0.2.53", "ns.example.com"));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_tls_service() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+type = "tls"
+host = "example.com"
+expiry_warning_days = [30, 14, 3]
+interval_seconds = 3600
+max_retries = 3
+retry_interval = 3
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+        let service = &config.services[0];
+
+        assert_eq!(service.check_type, CheckType::Tls);
+        assert_eq!(service.target(), "tls://example.com:443");
+        assert_eq!(service.expiry_warning_days, Some(vec![30, 14, 3]));
+
+        let result = FluxaConfig::from_str(&fluxa_configuration.replace("[30, 14, 3]", "[7, 0]"));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_heartbeat_service() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+type = "heartbeat"
+token = "nightly-backup"
+period = 86400
+grace = 3600
+interval_seconds = 60
+max_retries = 0
+retry_interval = 0
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+        let service = &config.services[0];
+
+        assert_eq!(service.check_type, CheckType::Heartbeat);
+        assert_eq!(service.target(), "heartbeat://nigh…");
+        assert_eq!(service.period, Some(Duration::from_secs(86400)));
+        assert_eq!(service.grace, Some(Duration::from_secs(3600)));
+
+        let duplicate = format!(
+            "{}\n[[services]]\n{}",
+            fluxa_configuration,
+            fluxa_configuration.split("[[services]]").nth(1).unwrap()
+        );
+
+        assert!(FluxaConfig::from_str(&duplicate).is_err());
+        assert!(
+            FluxaConfig::from_str(&fluxa_configuration.replace("nightly-backup", "a/b")).is_err()
+        );
+
+        // Tokens sharing the shown start are still distinct services
+        let same_start = format!(
+            "{}\n[[services]]\n{}",
+            fluxa_configuration,
+            fluxa_configuration
+                .split("[[services]]")
+                .nth(1)
+                .unwrap()
+                .replace("nightly-backup", "nightly-report")
+        );
+        let config = FluxaConfig::from_str(&same_start).unwrap();
+        assert_eq!(config.services[0].target(), config.services[1].target());
+        assert_ne!(config.services[0].id(), config.services[1].id());
+
+        let token_file =
+            std::env::temp_dir().join(format!("fluxa-{}-heartbeat-token", std::process::id()));
+        fs::write(&token_file, "from-file\n").unwrap();
+        std::env::set_var("FLUXA_SETTINGS_HEARTBEAT_TOKEN", "from-env");
+        let from_env = fluxa_configuration.replace(
+            "\"nightly-backup\"",
+            "\"${FLUXA_SETTINGS_HEARTBEAT_TOKEN}\"",
+        );
+        let from_file = fluxa_configuration.replace(
+            "token = \"nightly-backup\"",
+            &format!("token_file = {:?}", token_file),
+        );
+        let both = format!("{}token_file = {:?}\n", fluxa_configuration, token_file);
+        assert_eq!(
+            FluxaConfig::from_str(&from_env).unwrap().services[0].token,
+            Some("from-env".to_string())
+        );
+        assert_eq!(
+            FluxaConfig::from_str(&from_file).unwrap().services[0].token,
+            Some("from-file".to_string())
+        );
+        let error = FluxaConfig::from_str(&both).unwrap_err().to_string();
+        assert!(
+            error.contains("set either token or token_file"),
+            "{}",
+            error
+        );
+        fs::remove_file(&token_file).unwrap();
+
+        // The token authenticates pings, errors show only its start
+        for invalid in [
+            fluxa_configuration.replace("period = 86400\n", ""),
+            fluxa_configuration.replace("max_retries = 0\n", ""),
+        ] {
+            let error = FluxaConfig::from_str(&invalid).unwrap_err().to_string();
+            assert!(error.contains("heartbeat://nigh…"), "{}", error);
+            assert!(!error.contains("nightly-backup"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_exec_service() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+type = "exec"
+command = "/usr/local/bin/check_wan.sh"
+args = ["--interface", "eth0"]
+env = { PING_TARGET = "192.0.2.1" }
+timeout = 20
+interval_seconds = 60
+max_retries = 1
+retry_interval = 5
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+        let service = &config.services[0];
+
+        assert_eq!(service.check_type, CheckType::Exec);
+        assert_eq!(service.target(), "exec:///usr/local/bin/check_wan.sh");
+        assert_eq!(service.args, vec!["--interface", "eth0"]);
+        assert_eq!(
+            service.env.get("PING_TARGET"),
+            Some(&"192.0.2.1".to_string())
+        );
+
+        let result = FluxaConfig::from_str(&fluxa_configuration.replace(
+            "command = \"/usr/local/bin/check_wan.sh\"",
+            "command = \"\"",
+        ));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_local_services() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+type = "disk"
+path = "/data"
+min_free_percent = 10
+min_free_inodes_percent = 5
+interval_seconds = 300
+max_retries = 0
+retry_interval = 0
+
+[[services]]
+type = "file"
+path = "/backup/db.dump"
+max_age = 90000
+interval_seconds = 300
+max_retries = 0
+retry_interval = 0
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+
+        assert_eq!(config.services[0].check_type, CheckType::Disk);
+        assert_eq!(config.services[0].min_free_percent, Some(10));
+        assert_eq!(config.services[1].target(), "file:///backup/db.dump");
+
+        for invalid in [
+            ("min_free_percent = 10", "min_free_percent = 110"),
+            ("max_age = 90000", "max_age = 0"),
+        ] {
+            let result = FluxaConfig::from_str(&fluxa_configuration.replace(invalid.0, invalid.1));
+
+            assert!(result.is_err(), "{} should be rejected", invalid.1);
+        }
+    }
+
+    #[test]
+    fn test_process_service() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+type = "process"
+process_name = "dnsmasq"
+interval_seconds = 60
+max_retries = 1
+retry_interval = 5
+        "#;
+        let result = FluxaConfig::from_str(fluxa_configuration);
+
+        // Processes are looked up in /proc
+        if !cfg!(target_os = "linux") {
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                "Configuration error Service process://dnsmasq: \
+                 process checks are supported only on Linux"
+            );
+            return;
+        }
+        assert_eq!(result.unwrap().services[0].target(), "process://dnsmasq");
+
+        let both = fluxa_configuration.replace(
+            "process_name = \"dnsmasq\"",
+            "process_name = \"dnsmasq\"\npid_file = \"/run/dnsmasq.pid\"",
+        );
+        assert!(FluxaConfig::from_str(&both).is_err());
+    }
+
+    #[test]
+    fn test_datastore_services() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+type = "redis"
+host = "localhost"
+password = "secret"
+interval_seconds = 60
+max_retries = 3
+retry_interval = 3
+
+[[services]]
+type = "postgres"
+host = "db.local"
+port = 6432
+username = "monitor"
+database = "app"
+interval_seconds = 60
+max_retries = 3
+retry_interval = 3
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+
+        assert_eq!(config.services[0].check_type, CheckType::Redis);
+        assert_eq!(config.services[0].target(), "redis://localhost:6379");
+        assert_eq!(config.services[0].password, Some("secret".to_string()));
+        assert_eq!(config.services[1].check_type, CheckType::Postgres);
+        assert_eq!(config.services[1].target(), "postgres://db.local:6432");
+        assert_eq!(config.services[1].database, Some("app".to_string()));
+
+        let result = FluxaConfig::from_str(&fluxa_configuration.replace("host = \"db.local\"", ""));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_websocket_service() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+type = "websocket"
+url = "wss://example.com/live"
+headers = { Authorization = "Bearer token" }
+send = '{"type":"ping"}'
+expect = "pong"
+interval_seconds = 60
+max_retries = 3
+retry_interval = 3
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+        let service = &config.services[0];
+
+        assert_eq!(service.check_type, CheckType::Websocket);
+        assert_eq!(service.target(), "wss://example.com/live");
+        assert_eq!(service.expect, Some("pong".to_string()));
+
+        let result = FluxaConfig::from_str(&fluxa_configuration.replace("wss://", "https://"));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_grpc_service() {
+        let fluxa_configuration = r#"
+[fluxa]
+listen = "127.0.0.1:8080"
+
+[[services]]
+type = "grpc"
+url = "https://api.example.com:50051"
+grpc_service = "billing.v1.Invoices"
+interval_seconds = 60
+max_retries = 3
+retry_interval = 3
+        "#;
+        let config = FluxaConfig::from_str(fluxa_configuration).unwrap();
+        let service = &config.services[0];
+
+        assert_eq!(service.check_type, CheckType::Grpc);
+        assert_eq!(service.target(), "https://api.example.com:50051");
+        assert_eq!(
+            service.grpc_service,
+            Some("billing.v1.Invoices".to_string())
+        );
+    }
+
     #[test]
     fn test_configuration_error() {
         let fluxa_configuration = "";